@@ -1,23 +1,38 @@
 //! Main module.
 
+use std::collections::BTreeMap;
 use std::env::args;
 use std::error::Error;
 use std::result::Result;
 use std::time::Instant;
 
+use fimfareader::archive::difference;
+use fimfareader::archive::intersection;
+use fimfareader::archive::union;
 use fimfareader::archive::Fetcher;
-use fimfareader_query::parse;
+use fimfareader::archive::Story;
+use fimfareader::archive::StoryId;
+use fimfareader_query::parse_with;
+use fimfareader_query::Diagnostic;
+use fimfareader_query::Limits;
+use fimfareader_query::Query;
+use fimfareader_query::QueryLibrary;
 use rustyline::DefaultEditor;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let argv = args().collect::<Vec<String>>();
     let mut editor = DefaultEditor::new()?;
 
-    if argv.len() != 2 {
-        eprintln!("Usage: fimfareader <ARCHIVE>");
+    if argv.len() != 2 && argv.len() != 3 {
+        eprintln!("Usage: fimfareader <ARCHIVE> [QUERIES]");
         std::process::exit(1);
     }
 
+    let library = match argv.get(2) {
+        Some(path) => QueryLibrary::load(path)?,
+        None => QueryLibrary::new(),
+    };
+
     println!("Hellopaca, World!");
 
     let start = Instant::now();
@@ -28,35 +43,142 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Finished loading in {finish:?}.");
     println!("The archive contains {count} stories.");
 
+    let mut sets = BTreeMap::new();
+    let mut last = Vec::new();
+
     while let Ok(line) = editor.readline(">>> ") {
         editor.add_history_entry(&line)?;
 
-        let filter = match parse(&line) {
-            Ok(filter) => filter,
+        if let Some(name) = line.strip_prefix("save ") {
+            let name = name.trim();
+
+            println!("Saved {} stories as {}.", last.len(), name);
+            sets.insert(name.to_string(), last.clone());
+            continue;
+        }
+
+        if let Some(combined) = combine(&sets, &line) {
+            match combined {
+                Ok(ids) => {
+                    let stories: Vec<&Story> = ids
+                        .iter()
+                        .filter_map(|id| fetcher.fetch(*id))
+                        .collect();
+
+                    println!("Combined into {} stories!", stories.len());
+                    print(&stories);
+                    last = ids;
+                }
+                Err(message) => println!("{message}"),
+            }
+
+            continue;
+        }
+
+        let (explain, line) = match line.strip_prefix("explain ") {
+            Some(line) => (true, line),
+            None => (false, line.as_str()),
+        };
+
+        let limits = Limits::new().allow_files(true);
+
+        let query = match line.strip_prefix('@') {
+            Some(saved) => saved_query(&library, saved, &limits),
+            None => parse_with(line, &limits),
+        };
+
+        let query = match query {
+            Ok(query) => query,
             Err(error) => {
                 println!("{}", error);
+
+                let source = error.source();
+                let diagnostic = source.and_then(|e| e.downcast_ref());
+
+                if let Some(diagnostic) = diagnostic {
+                    println!("{}", Diagnostic::snippet(diagnostic));
+                }
+
                 continue;
             }
         };
 
+        if explain {
+            print!("{}", query.explain());
+            continue;
+        }
+
         let start = Instant::now();
-        let stories = fetcher.filter(&filter);
+        let stories = query.apply(&fetcher);
         let finish = (Instant::now() - start).as_millis();
         let count = stories.len();
 
         println!("Found {} stories in {} milliseconds!", count, finish);
+        print(&stories);
 
-        if count > 32 {
-            continue;
-        }
+        last = stories.iter().map(|story| story.id).collect();
+    }
+
+    Ok(())
+}
+
+fn print(stories: &[&Story]) {
+    if stories.len() > 32 {
+        return;
+    }
+
+    for story in stories.iter() {
+        let key = &story.id;
+        let title = &story.title;
+
+        println!("[{}] {}", key, title);
+    }
+}
+
+fn combine(
+    sets: &BTreeMap<String, Vec<StoryId>>,
+    line: &str,
+) -> Option<Result<Vec<StoryId>, String>> {
+    let mut words = line.split_whitespace();
 
-        for story in stories.iter() {
-            let key = &story.id;
-            let title = &story.title;
+    let operation = match words.next()? {
+        "union" => union,
+        "intersect" => intersection,
+        "difference" => difference,
+        _ => return None,
+    };
 
-            println!("[{}] {}", key, title);
+    let mut operands = Vec::new();
+
+    for name in words {
+        match sets.get(name) {
+            Some(ids) => operands.push(ids),
+            None => return Some(Err(format!("Unknown result set {name}."))),
         }
     }
 
-    Ok(())
+    let Some((first, rest)) = operands.split_first() else {
+        return Some(Err("Expected result set names.".into()));
+    };
+
+    let combined = rest
+        .iter()
+        .fold(first.to_vec(), |acc, ids| operation(&acc, ids));
+
+    Some(Ok(combined))
+}
+
+fn saved_query(
+    library: &QueryLibrary,
+    line: &str,
+    limits: &Limits,
+) -> fimfareader::error::Result<Query> {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or_default();
+
+    let params: Vec<(&str, &str)> = words
+        .map(|word| word.split_once('=').unwrap_or((word, "")))
+        .collect();
+
+    library.instantiate_with(name, &params, limits)
 }
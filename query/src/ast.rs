@@ -0,0 +1,422 @@
+//! Query syntax tree.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::path::PathBuf;
+use std::result::Result as StdResult;
+
+use fimfareader::archive::Lookup;
+use fimfareader::archive::Story;
+use fimfareader::archive::StoryId;
+use fimfareader::error::Error;
+use fimfareader::error::ErrorBuilder;
+use fimfareader::error::ErrorKind;
+use fimfareader::error::Result;
+
+use crate::context::Context;
+use crate::filter::Filter;
+use crate::limits::Limits;
+use crate::optimizer::cost;
+use crate::optimizer::lookup;
+use crate::optimizer::optimize;
+use crate::optimizer::versus;
+use crate::optimizer::Flags;
+use crate::parser::Predicate;
+use crate::parser::Source;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Op {
+    Exact,
+    NotEqual,
+    Fuzzy,
+    LessThan,
+    AtMost,
+    MoreThan,
+    AtLeast,
+    Approximate(Option<usize>),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Case {
+    Sensitive,
+    Insensitive,
+}
+
+#[derive(Clone)]
+pub struct Term {
+    pub field: String,
+    pub op: Op,
+    pub case: Option<Case>,
+    pub fold: bool,
+    pub value: String,
+    pub(crate) source: Source,
+}
+
+#[derive(Clone)]
+pub struct Has {
+    pub field: String,
+    pub(crate) check: fn(&Story) -> bool,
+}
+
+#[derive(Clone)]
+pub struct Compare {
+    pub left: String,
+    pub op: Op,
+    pub right: String,
+    pub(crate) sources: Box<(Source, Source)>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Sequel {
+    Of(StoryId),
+    Exists,
+}
+
+#[derive(Clone, Debug)]
+pub enum Members {
+    List(Vec<StoryId>),
+    File(PathBuf),
+}
+
+#[derive(Clone, Debug)]
+pub enum Ast {
+    Term(Term),
+    Has(Has),
+    In(Members),
+    Sequel(Sequel),
+    Compare(Compare),
+    Not(Box<Ast>),
+    And(Vec<Ast>),
+    Or(Vec<Ast>),
+}
+
+impl Ast {
+    pub fn normalize(self) -> Ast {
+        match self {
+            Ast::Not(ast) => match ast.normalize() {
+                Ast::Not(ast) => *ast,
+                ast => Ast::Not(Box::new(ast)),
+            },
+            Ast::And(asts) => flatten(asts, Ast::And, |ast| match ast {
+                Ast::And(asts) => Ok(asts),
+                ast => Err(ast),
+            }),
+            Ast::Or(asts) => flatten(asts, Ast::Or, |ast| match ast {
+                Ast::Or(asts) => Ok(asts),
+                ast => Err(ast),
+            }),
+            ast => ast,
+        }
+    }
+
+    pub fn optimize(self) -> Ast {
+        match self.normalize() {
+            Ast::Not(ast) => Ast::Not(Box::new(ast.optimize())),
+            Ast::And(asts) => Ast::And(reorder(asts)),
+            Ast::Or(asts) => Ast::Or(reorder(asts)),
+            ast => ast,
+        }
+    }
+
+    pub fn cost(&self) -> u32 {
+        match self {
+            Ast::Term(term) => cost(&term.source, term.op),
+            Ast::Has(_) | Ast::In(_) | Ast::Sequel(_) => 1,
+            Ast::Compare(_) => 2,
+            Ast::Not(ast) => ast.cost(),
+            Ast::And(asts) | Ast::Or(asts) => asts.iter().map(Ast::cost).sum(),
+        }
+    }
+
+    pub fn lookup(&self) -> Option<Lookup> {
+        match self {
+            Ast::Term(term) => lookup(term),
+            Ast::Sequel(Sequel::Of(id)) => Some(Lookup::Sequels(*id)),
+            Ast::And(asts) => asts.iter().find_map(Ast::lookup),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn explain(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+
+        let children = match self {
+            Ast::Term(term) => {
+                out.push_str(&format!("{indent}{term}\n"));
+                return;
+            }
+            Ast::Has(has) => {
+                out.push_str(&format!("{indent}has {}\n", has.field));
+                return;
+            }
+            Ast::In(members) => {
+                out.push_str(&format!("{indent}id in {members}\n"));
+                return;
+            }
+            Ast::Compare(compare) => {
+                out.push_str(&format!("{indent}{compare}\n"));
+                return;
+            }
+            Ast::Sequel(Sequel::Of(id)) => {
+                out.push_str(&format!("{indent}sequel of {id}\n"));
+                return;
+            }
+            Ast::Sequel(Sequel::Exists) => {
+                out.push_str(&format!("{indent}has sequel\n"));
+                return;
+            }
+            Ast::Not(ast) => {
+                out.push_str(&format!("{indent}not\n"));
+                return ast.explain(depth + 1, out);
+            }
+            Ast::And(asts) if asts.is_empty() => {
+                out.push_str(&format!("{indent}everything\n"));
+                return;
+            }
+            Ast::And(asts) => {
+                out.push_str(&format!("{indent}all of\n"));
+                asts
+            }
+            Ast::Or(asts) => {
+                out.push_str(&format!("{indent}any of\n"));
+                asts
+            }
+        };
+
+        for child in children {
+            child.explain(depth + 1, out);
+        }
+    }
+
+    pub fn compile(&self) -> Result<Filter> {
+        let context = Context::default();
+        let predicate = self.compile_with(&Limits::default(), &context)?;
+
+        Ok(Filter::with_context(predicate, context))
+    }
+
+    pub(crate) fn compile_with(
+        &self,
+        limits: &Limits,
+        context: &Context,
+    ) -> Result<Predicate> {
+        let filter: Predicate = match self {
+            Ast::Term(term) => {
+                let flags = Flags {
+                    case: term.case,
+                    fold: term.fold,
+                    size: limits.max_regex_size,
+                };
+
+                optimize(term.source.clone(), term.op, flags, &term.value)?
+            }
+            Ast::Has(has) => Box::new(has.check),
+            Ast::In(members) => {
+                limits.check_members(members)?;
+                let ids = members.load()?;
+                Box::new(move |story| ids.contains(&story.id))
+            }
+            Ast::Sequel(Sequel::Of(id)) => {
+                let id = Some(*id);
+                Box::new(move |story| story.prequel == id)
+            }
+            Ast::Sequel(Sequel::Exists) => {
+                let context = context.clone();
+                Box::new(move |story| context.has_sequel(story))
+            }
+            Ast::Compare(compare) => {
+                let (left, right) = *compare.sources.clone();
+                versus(left, compare.op, right)?
+            }
+            Ast::Not(ast) => {
+                let filter = ast.compile_with(limits, context)?;
+                Box::new(move |story| !filter(story))
+            }
+            Ast::And(asts) => {
+                let filters = compile(asts, limits, context)?;
+                Box::new(move |story| filters.iter().all(|f| f(story)))
+            }
+            Ast::Or(asts) => {
+                let filters = compile(asts, limits, context)?;
+                Box::new(move |story| filters.iter().any(|f| f(story)))
+            }
+        };
+
+        Ok(filter)
+    }
+}
+
+impl Members {
+    pub fn load(&self) -> Result<HashSet<StoryId>> {
+        match self {
+            Members::List(ids) => Ok(ids.iter().copied().collect()),
+            Members::File(path) => load(path),
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<HashSet<StoryId>> {
+    let Ok(text) = read_to_string(path) else {
+        let error = Error::usage("Could not read story id file");
+        return Err(error.with_path(path.display()));
+    };
+
+    let mut ids = HashSet::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Ok(id) = line.parse() else {
+            return Err(ErrorBuilder::new(ErrorKind::UsageError)
+                .message("Invalid story id")
+                .path(path.display())
+                .line(number + 1)
+                .build());
+        };
+
+        ids.insert(id);
+    }
+
+    Ok(ids)
+}
+
+fn flatten<W, U>(asts: Vec<Ast>, wrap: W, unwrap: U) -> Ast
+where
+    W: Fn(Vec<Ast>) -> Ast,
+    U: Fn(Ast) -> StdResult<Vec<Ast>, Ast>,
+{
+    let mut flat = Vec::with_capacity(asts.len());
+
+    for ast in asts {
+        match unwrap(ast.normalize()) {
+            Ok(asts) => flat.extend(asts),
+            Err(ast) => flat.push(ast),
+        }
+    }
+
+    match flat.len() {
+        1 => flat.remove(0),
+        _ => wrap(flat),
+    }
+}
+
+fn reorder(asts: Vec<Ast>) -> Vec<Ast> {
+    let mut asts: Vec<Ast> = asts.into_iter().map(Ast::optimize).collect();
+
+    asts.sort_by_key(Ast::cost);
+    asts
+}
+
+fn compile(
+    asts: &[Ast],
+    limits: &Limits,
+    context: &Context,
+) -> Result<Vec<Predicate>> {
+    asts.iter()
+        .map(|ast| ast.compile_with(limits, context))
+        .collect()
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let symbol = match self {
+            Op::Approximate(Some(limit)) => return write!(f, "~={limit}"),
+            Op::Approximate(None) => "~=",
+            Op::Exact => "=",
+            Op::NotEqual => "!=",
+            Op::Fuzzy => ":",
+            Op::LessThan => "<",
+            Op::AtMost => "<=",
+            Op::MoreThan => ">",
+            Op::AtLeast => ">=",
+        };
+
+        write!(f, "{}", symbol)
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let value = self.value.replace('\\', "\\\\").replace('"', "\\\"");
+
+        let case = match self.case {
+            Some(Case::Sensitive) => "cs",
+            Some(Case::Insensitive) => "ci",
+            None => "",
+        };
+
+        let fold = match self.fold {
+            true => "ai",
+            false => "",
+        };
+
+        let (field, op) = (&self.field, self.op);
+
+        match (case, fold) {
+            ("", "") => write!(f, "{} {} \"{}\"", field, op, value),
+            _ => write!(f, "{} {}[{}{}] \"{}\"", field, op, case, fold, value),
+        }
+    }
+}
+
+impl Display for Members {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Members::List(ids) => {
+                let ids: Vec<String> =
+                    ids.iter().map(ToString::to_string).collect();
+
+                write!(f, "({})", ids.join(", "))
+            }
+            Members::File(path) => {
+                let path = path.display().to_string();
+                let path = path.replace('\\', "\\\\").replace('"', "\\\"");
+
+                write!(f, "file(\"{}\")", path)
+            }
+        }
+    }
+}
+
+impl Display for Compare {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
+impl Debug for Term {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Term")
+            .field("field", &self.field)
+            .field("op", &self.op)
+            .field("case", &self.case)
+            .field("fold", &self.fold)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl Debug for Compare {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Compare")
+            .field("left", &self.left)
+            .field("op", &self.op)
+            .field("right", &self.right)
+            .finish()
+    }
+}
+
+impl Debug for Has {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Has").field("field", &self.field).finish()
+    }
+}
@@ -0,0 +1,368 @@
+//! Query builder.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::ops::Not;
+
+use fimfareader::archive::Order;
+use fimfareader::archive::SortKey;
+use fimfareader::error::Result;
+
+use crate::ast::Ast;
+use crate::ast::Case;
+use crate::ast::Op;
+use crate::ast::Term;
+use crate::context::Context;
+use crate::filter::Filter;
+use crate::limits::Limits;
+use crate::parser::named;
+use crate::query::Query;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Field {
+    Id,
+    Url,
+    Title,
+    Description,
+    ShortDescription,
+    Modified,
+    Updated,
+    Published,
+    Chapters,
+    Comments,
+    Dislikes,
+    Likes,
+    TotalViews,
+    Views,
+    Words,
+    LikeRatio,
+    WilsonScore,
+    ViewsPerWord,
+    MaxChapterWords,
+    MinChapterWords,
+    AvgChapterWords,
+    Rating,
+    Completion,
+    Submitted,
+    Visible,
+    AuthorName,
+    AuthorId,
+    AuthorJoined,
+    AuthorFollowers,
+    AuthorStories,
+    AuthorBlogPosts,
+    Path,
+    Tag,
+    Tags,
+    Character,
+    Genre,
+    Series,
+    Warning,
+}
+
+pub struct Condition(Field, Option<Case>, bool);
+
+pub struct QueryBuilder {
+    pub(crate) ast: Ast,
+    pub(crate) sort: Option<(SortKey, Order)>,
+    pub(crate) offset: usize,
+    pub(crate) limit: Option<usize>,
+    pub(crate) random: Option<(usize, Option<u64>)>,
+    pub(crate) limits: Limits,
+}
+
+impl Field {
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Url => "url",
+            Field::Title => "title",
+            Field::Description => "description",
+            Field::ShortDescription => "short description",
+            Field::Modified => "modified",
+            Field::Updated => "updated",
+            Field::Published => "published",
+            Field::Chapters => "chapters",
+            Field::Comments => "comments",
+            Field::Dislikes => "dislikes",
+            Field::Likes => "likes",
+            Field::TotalViews => "total views",
+            Field::Views => "views",
+            Field::Words => "words",
+            Field::LikeRatio => "like ratio",
+            Field::WilsonScore => "wilson score",
+            Field::ViewsPerWord => "views per word",
+            Field::MaxChapterWords => "max chapter words",
+            Field::MinChapterWords => "min chapter words",
+            Field::AvgChapterWords => "avg chapter words",
+            Field::Rating => "content rating",
+            Field::Completion => "completion status",
+            Field::Submitted => "submitted",
+            Field::Visible => "visible",
+            Field::AuthorName => "author name",
+            Field::AuthorId => "author id",
+            Field::AuthorJoined => "author joined",
+            Field::AuthorFollowers => "author followers",
+            Field::AuthorStories => "author stories",
+            Field::AuthorBlogPosts => "author blog posts",
+            Field::Path => "path",
+            Field::Tag => "tag",
+            Field::Tags => "tags",
+            Field::Character => "character",
+            Field::Genre => "genre",
+            Field::Series => "series",
+            Field::Warning => "warning",
+        }
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Query {
+    pub fn field(field: Field) -> Condition {
+        Condition(field, None, false)
+    }
+
+    pub fn builder() -> QueryBuilder {
+        QueryBuilder::default()
+    }
+}
+
+impl Condition {
+    pub fn case(self, case: Case) -> Self {
+        Condition(self.0, Some(case), self.2)
+    }
+
+    pub fn fold(self) -> Self {
+        Condition(self.0, self.1, true)
+    }
+
+    fn term(self, op: Op, value: impl ToString) -> QueryBuilder {
+        let term = Term {
+            field: self.0.name().into(),
+            op,
+            case: self.1,
+            fold: self.2,
+            value: value.to_string(),
+            source: named(self.0),
+        };
+
+        QueryBuilder::from(Ast::Term(term))
+    }
+
+    pub fn eq(self, value: impl ToString) -> QueryBuilder {
+        self.term(Op::Exact, value)
+    }
+
+    pub fn ne(self, value: impl ToString) -> QueryBuilder {
+        self.term(Op::NotEqual, value)
+    }
+
+    pub fn like(self, value: impl ToString) -> QueryBuilder {
+        self.term(Op::Fuzzy, value)
+    }
+
+    pub fn lt(self, value: impl ToString) -> QueryBuilder {
+        self.term(Op::LessThan, value)
+    }
+
+    pub fn le(self, value: impl ToString) -> QueryBuilder {
+        self.term(Op::AtMost, value)
+    }
+
+    pub fn gt(self, value: impl ToString) -> QueryBuilder {
+        self.term(Op::MoreThan, value)
+    }
+
+    pub fn ge(self, value: impl ToString) -> QueryBuilder {
+        self.term(Op::AtLeast, value)
+    }
+
+    pub fn approx(
+        self,
+        value: impl ToString,
+        limit: Option<usize>,
+    ) -> QueryBuilder {
+        self.term(Op::Approximate(limit), value)
+    }
+
+    pub fn between(
+        self,
+        start: impl ToString,
+        end: impl ToString,
+    ) -> QueryBuilder {
+        let value = format!("{}..{}", start.to_string(), end.to_string());
+
+        self.term(Op::Exact, value)
+    }
+}
+
+impl QueryBuilder {
+    pub fn and(self, other: QueryBuilder) -> Self {
+        let ast = Ast::And(vec![self.ast, other.ast]);
+
+        QueryBuilder { ast, ..self }
+    }
+
+    pub fn or(self, other: QueryBuilder) -> Self {
+        let ast = Ast::Or(vec![self.ast, other.ast]);
+
+        QueryBuilder { ast, ..self }
+    }
+
+    pub fn sort_by(mut self, key: SortKey, order: Order) -> Self {
+        self.sort = Some((key, order));
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn random(mut self, count: usize, seed: Option<u64>) -> Self {
+        self.random = Some((count, seed));
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn build(self) -> Result<Query> {
+        self.limits.check_clauses(&self.ast)?;
+
+        let ast = self.ast.optimize();
+        let context = Context::default();
+        let predicate = ast.compile_with(&self.limits, &context)?;
+        let filter = Filter::with_context(predicate, context);
+
+        Ok(Query {
+            ast,
+            filter,
+            sort: self.sort,
+            offset: self.offset,
+            limit: self.limit,
+            random: self.random,
+            budget: self.limits.budget,
+        })
+    }
+}
+
+impl Default for QueryBuilder {
+    fn default() -> Self {
+        Ast::And(Vec::new()).into()
+    }
+}
+
+impl From<Ast> for QueryBuilder {
+    fn from(ast: Ast) -> Self {
+        QueryBuilder {
+            ast,
+            sort: None,
+            offset: 0,
+            limit: None,
+            random: None,
+            limits: Limits::default(),
+        }
+    }
+}
+
+impl Not for QueryBuilder {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        let ast = Ast::Not(Box::new(self.ast));
+
+        QueryBuilder { ast, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::discriminant;
+
+    use super::Field;
+    use crate::ast::Ast;
+    use crate::ast::Term;
+    use crate::parser::parse;
+    use crate::parser::Source;
+    use crate::query::Query;
+
+    const FIELDS: &[(Field, &str)] = &[
+        (Field::Id, "1"),
+        (Field::Url, "fimfiction"),
+        (Field::Title, "x"),
+        (Field::Description, "x"),
+        (Field::ShortDescription, "x"),
+        (Field::Modified, "2020-01-01"),
+        (Field::Updated, "2020-01-01"),
+        (Field::Published, "2020-01-01"),
+        (Field::Chapters, "1"),
+        (Field::Comments, "1"),
+        (Field::Dislikes, "1"),
+        (Field::Likes, "1"),
+        (Field::TotalViews, "1"),
+        (Field::Views, "1"),
+        (Field::Words, "1"),
+        (Field::LikeRatio, "0.5"),
+        (Field::WilsonScore, "0.5"),
+        (Field::ViewsPerWord, "0.5"),
+        (Field::MaxChapterWords, "1"),
+        (Field::MinChapterWords, "1"),
+        (Field::AvgChapterWords, "1"),
+        (Field::Rating, "teen"),
+        (Field::Completion, "complete"),
+        (Field::Submitted, "true"),
+        (Field::Visible, "true"),
+        (Field::AuthorName, "x"),
+        (Field::AuthorId, "1"),
+        (Field::AuthorJoined, "2020-01-01"),
+        (Field::AuthorFollowers, "1"),
+        (Field::AuthorStories, "1"),
+        (Field::AuthorBlogPosts, "1"),
+        (Field::Path, "x"),
+        (Field::Tag, "x"),
+        (Field::Tags, "x"),
+        (Field::Character, "x"),
+        (Field::Genre, "x"),
+        (Field::Series, "x"),
+        (Field::Warning, "x"),
+    ];
+
+    fn term(query: &Query) -> &Term {
+        match query.ast() {
+            Ast::Term(term) => term,
+            ast => panic!("Unexpected {ast:?}"),
+        }
+    }
+
+    #[test]
+    fn matches_parsed_queries() {
+        for &(field, value) in FIELDS {
+            let built = Query::field(field).eq(value).build().unwrap();
+            let text = term(&built).to_string();
+            let parsed = parse(&text).unwrap();
+
+            let (built, parsed) = (term(&built), term(&parsed));
+
+            assert_eq!(parsed.to_string(), text);
+
+            match (&built.source, &parsed.source) {
+                (Source::Tag(a), Source::Tag(b)) => assert_eq!(a, b),
+                (a, b) => assert_eq!(discriminant(a), discriminant(b)),
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Archive context.
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::io::Seek;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use fimfareader::archive::Fetcher;
+use fimfareader::archive::Story;
+use fimfareader::archive::StoryId;
+
+type Binding = (Range<usize>, Arc<HashSet<StoryId>>);
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Context {
+    bindings: Arc<RwLock<Vec<Binding>>>,
+}
+
+fn address(story: &Story) -> usize {
+    story as *const Story as usize
+}
+
+impl Context {
+    pub fn bind<T: Read + Seek>(&self, fetcher: &Fetcher<T>) {
+        let stories = fetcher.stories();
+
+        let range = match (stories.first(), stories.last()) {
+            (Some(first), Some(last)) => address(first)..address(last) + 1,
+            _ => return,
+        };
+
+        let prequels = Arc::new(fetcher.prequels().collect());
+        let mut bindings = self.bindings.write().unwrap();
+
+        bindings.retain(|(other, _)| {
+            other.end <= range.start || range.end <= other.start
+        });
+
+        bindings.push((range, prequels));
+    }
+
+    pub fn has_sequel(&self, story: &Story) -> bool {
+        let address = address(story);
+        let bindings = self.bindings.read().unwrap();
+
+        bindings
+            .iter()
+            .find(|(range, _)| range.contains(&address))
+            .is_some_and(|(_, prequels)| prequels.contains(&story.id))
+    }
+}
@@ -0,0 +1,139 @@
+//! Query diagnostics.
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use fimfareader::error::Error;
+use fimfareader::error::ErrorBuilder;
+use fimfareader::error::ErrorKind;
+
+use crate::distance::closest;
+use crate::parser::operator;
+use crate::parser::source;
+use crate::parser::FIELDS;
+use crate::parser::OPERATORS;
+
+const SKIP: &[char] = &[',', '|', '!', '(', ' ', '\t'];
+
+const START: &[char] = &[',', '|', '('];
+
+const STOP: &[char] = &[':', '=', '<', '>', '!', ',', '|', '(', ')'];
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub query: String,
+    pub position: usize,
+    pub message: String,
+    pub expected: Vec<&'static str>,
+    pub suggestion: Option<&'static str>,
+}
+
+impl Diagnostic {
+    fn new(query: &str, position: usize, message: impl ToString) -> Self {
+        Diagnostic {
+            query: query.into(),
+            position,
+            message: message.to_string(),
+            expected: Vec::new(),
+            suggestion: None,
+        }
+    }
+
+    fn expected(mut self, expected: &[&'static str]) -> Self {
+        self.expected = expected.to_vec();
+        self
+    }
+
+    pub fn snippet(&self) -> String {
+        let column = self.query[..self.position].chars().count();
+
+        format!("{}\n{}^", self.query, " ".repeat(column))
+    }
+}
+
+fn unbalanced(query: &str) -> Option<usize> {
+    let mut open = Vec::new();
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in query.char_indices() {
+        match (c, quoted, escaped) {
+            (_, _, true) => (),
+            ('"', _, _) => quoted = !quoted,
+            ('(', false, _) => open.push(i),
+            (')', false, _) if open.pop().is_none() => return Some(i),
+            _ => (),
+        }
+
+        escaped = c == '\\' && !escaped;
+    }
+
+    open.pop()
+}
+
+pub(crate) fn diagnose(query: &str, position: usize) -> Diagnostic {
+    if let Some(position) = unbalanced(query) {
+        let diagnostic =
+            Diagnostic::new(query, position, "Unbalanced parenthesis");
+        return diagnostic.expected(&["(", ")"]);
+    }
+
+    let start = match query[position..].trim_start().starts_with([',', '|']) {
+        true => position,
+        false => query[..position].rfind(START).map_or(0, |i| i + 1),
+    };
+
+    let rest = query[start..].trim_start_matches(SKIP);
+    let position = query.len() - rest.len();
+
+    if rest.is_empty() {
+        let diagnostic =
+            Diagnostic::new(query, position, "Unexpected end of query");
+        return diagnostic.expected(FIELDS);
+    }
+
+    let Ok((after, _)) = source(rest) else {
+        let word = rest.split(STOP).next().unwrap_or_default().trim();
+        let message = format!("Unknown field {word}");
+
+        return Diagnostic {
+            suggestion: closest(word, FIELDS),
+            ..Diagnostic::new(query, position, message).expected(FIELDS)
+        };
+    };
+
+    let field = rest[..rest.len() - after.len()].trim();
+    let position = query.len() - after.trim_start().len();
+
+    match operator(after) {
+        Err(_) => {
+            let message = format!("Expected operator after {field}");
+            Diagnostic::new(query, position, message).expected(OPERATORS)
+        }
+        Ok(_) => Diagnostic::new(query, position, "Unexpected input"),
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} at position {}", self.message, self.position)?;
+
+        match self.suggestion {
+            Some(suggestion) => write!(f, ", did you mean {}", suggestion),
+            None => Ok(()),
+        }
+    }
+}
+
+impl StdError for Diagnostic {}
+
+impl From<Diagnostic> for Error {
+    fn from(diagnostic: Diagnostic) -> Self {
+        ErrorBuilder::new(ErrorKind::QueryError)
+            .message(&diagnostic)
+            .source(diagnostic)
+            .build()
+    }
+}
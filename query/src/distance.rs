@@ -0,0 +1,56 @@
+//! Edit distance.
+
+pub(crate) fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut before = previous.clone();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+
+        before.clone_from(&previous);
+        previous.clone_from(&current);
+    }
+
+    previous[b.len()]
+}
+
+pub(crate) fn threshold(value: &str) -> usize {
+    value.chars().count().div_ceil(4)
+}
+
+pub(crate) fn within(a: &str, b: &str, limit: usize) -> bool {
+    let lengths = a.chars().count().abs_diff(b.chars().count());
+
+    lengths <= limit && distance(a, b) <= limit
+}
+
+pub(crate) fn closest<'a>(word: &str, options: &[&'a str]) -> Option<&'a str> {
+    let limit = (word.chars().count() / 3).max(1);
+    let word = word.to_lowercase();
+
+    let scored = options
+        .iter()
+        .map(|option| (distance(&word, option), *option));
+    let (score, option) = scored.min_by_key(|(score, _)| *score)?;
+
+    match score <= limit {
+        true => Some(option),
+        false => None,
+    }
+}
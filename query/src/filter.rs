@@ -0,0 +1,104 @@
+//! Story filters.
+
+use std::io::Read;
+use std::io::Seek;
+use std::ops::Not;
+
+use fimfareader::archive::Fetcher;
+use fimfareader::archive::Story;
+
+use crate::context::Context;
+use crate::parser::Predicate;
+use crate::query::Query;
+
+pub struct Filter {
+    pub(crate) predicate: Predicate,
+    pub(crate) contexts: Vec<Context>,
+}
+
+impl Filter {
+    pub fn new<F>(function: F) -> Self
+    where
+        F: Fn(&Story) -> bool + Send + Sync + 'static,
+    {
+        Filter {
+            predicate: Box::new(function),
+            contexts: Vec::new(),
+        }
+    }
+
+    pub(crate) fn with_context(
+        predicate: Predicate,
+        context: Context,
+    ) -> Self {
+        Filter {
+            predicate,
+            contexts: vec![context],
+        }
+    }
+
+    fn combine<F>(self, other: Filter, function: F) -> Self
+    where
+        F: Fn(&Predicate, &Predicate, &Story) -> bool + Send + Sync + 'static,
+    {
+        let (a, b) = (self.predicate, other.predicate);
+        let mut contexts = self.contexts;
+        contexts.extend(other.contexts);
+
+        Filter {
+            predicate: Box::new(move |story| function(&a, &b, story)),
+            contexts,
+        }
+    }
+
+    pub fn matches(&self, story: &Story) -> bool {
+        (self.predicate)(story)
+    }
+
+    pub fn and(self, other: impl Into<Filter>) -> Self {
+        self.combine(other.into(), |a, b, story| a(story) && b(story))
+    }
+
+    pub fn or(self, other: impl Into<Filter>) -> Self {
+        self.combine(other.into(), |a, b, story| a(story) || b(story))
+    }
+
+    pub(crate) fn bind<T: Read + Seek>(&self, fetcher: &Fetcher<T>) {
+        for context in &self.contexts {
+            context.bind(fetcher);
+        }
+    }
+
+    pub fn apply<'a, T>(&self, fetcher: &'a Fetcher<T>) -> Vec<&'a Story>
+    where
+        T: Read + Seek,
+    {
+        self.bind(fetcher);
+        fetcher.filter(&self.predicate)
+    }
+}
+
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        let filter = self.predicate;
+
+        Filter {
+            predicate: Box::new(move |story| !filter(story)),
+            contexts: self.contexts,
+        }
+    }
+}
+
+impl From<fn(&Story) -> bool> for Filter {
+    fn from(function: fn(&Story) -> bool) -> Self {
+        Filter::new(function)
+    }
+}
+
+impl From<Query> for Filter {
+    fn from(query: Query) -> Self {
+        query.filter
+    }
+}
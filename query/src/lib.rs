@@ -1,6 +1,32 @@
 //! Query module.
 
+mod ast;
+mod builder;
+mod context;
+mod diagnostic;
+mod distance;
+mod filter;
+mod library;
+mod limits;
 mod optimizer;
 mod parser;
+mod query;
 
+pub use self::ast::Ast;
+pub use self::ast::Case;
+pub use self::ast::Compare;
+pub use self::ast::Has;
+pub use self::ast::Members;
+pub use self::ast::Op;
+pub use self::ast::Sequel;
+pub use self::ast::Term;
+pub use self::builder::Condition;
+pub use self::builder::Field;
+pub use self::builder::QueryBuilder;
+pub use self::diagnostic::Diagnostic;
+pub use self::filter::Filter;
+pub use self::library::QueryLibrary;
+pub use self::limits::Limits;
 pub use self::parser::parse;
+pub use self::parser::parse_with;
+pub use self::query::Query;
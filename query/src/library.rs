@@ -0,0 +1,354 @@
+//! Saved queries.
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use fimfareader::error::Error;
+use fimfareader::error::ErrorBuilder;
+use fimfareader::error::ErrorKind;
+use fimfareader::error::Result;
+
+use crate::limits::Limits;
+use crate::parser::parse_with;
+use crate::query::Query;
+
+const TABLE: &str = "queries";
+
+#[derive(Clone, Debug, Default)]
+pub struct QueryLibrary {
+    queries: BTreeMap<String, String>,
+}
+
+impl QueryLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let Ok(text) = read_to_string(path) else {
+            let error = Error::usage("Could not read query library");
+            return Err(error.with_path(path.display()));
+        };
+
+        Self::from_toml(&text).map_err(|e| e.with_path(path.display()))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let mut library = Self::new();
+        let mut table = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let failure = |message| {
+                ErrorBuilder::new(ErrorKind::UsageError)
+                    .message(message)
+                    .line(number + 1)
+                    .build()
+            };
+
+            let line = strip(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let Some(header) = header.strip_suffix(']') else {
+                    return Err(failure("Invalid table header"));
+                };
+
+                if header.trim() != TABLE {
+                    return Err(failure("Unsupported table"));
+                }
+
+                table = Some(TABLE);
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(failure("Expected key and value"));
+            };
+
+            let Some(key) = name(key.trim()) else {
+                return Err(failure("Invalid key"));
+            };
+
+            let Some(value) = string(value.trim()) else {
+                return Err(failure("Invalid string value"));
+            };
+
+            if table.is_none() && key == TABLE {
+                return Err(failure("Unsupported table"));
+            }
+
+            if library.queries.insert(key, value).is_some() {
+                return Err(failure("Duplicate key"));
+            }
+        }
+
+        Ok(library)
+    }
+
+    pub fn insert(&mut self, name: impl ToString, query: impl ToString) {
+        self.queries.insert(name.to_string(), query.to_string());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.queries.get(name).map(String::as_str)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.queries.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    pub fn params(&self, name: &str) -> Vec<&str> {
+        let Some(template) = self.get(name) else {
+            return Vec::new();
+        };
+
+        let mut params = Vec::new();
+
+        for part in template.split('{').skip(1) {
+            if let Some((param, _)) = part.split_once('}') {
+                if !param.is_empty() && !params.contains(&param) {
+                    params.push(param);
+                }
+            }
+        }
+
+        params
+    }
+
+    pub fn instantiate(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+    ) -> Result<Query> {
+        self.instantiate_with(name, params, &Limits::default())
+    }
+
+    pub fn instantiate_with(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+        limits: &Limits,
+    ) -> Result<Query> {
+        let Some(template) = self.get(name) else {
+            return Err(Error::query(format!("Unknown saved query {name}")));
+        };
+
+        parse_with(&substitute(template, params)?, limits)
+    }
+}
+
+fn strip(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => (),
+        }
+
+        escaped = false;
+    }
+
+    line
+}
+
+fn name(key: &str) -> Option<String> {
+    let bare = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+
+    match key.starts_with(['"', '\'']) {
+        true => string(key),
+        false if !key.is_empty() && key.chars().all(bare) => Some(key.into()),
+        false => None,
+    }
+}
+
+fn string(value: &str) -> Option<String> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        let literal = literal.strip_suffix('\'')?;
+
+        return match literal.contains('\'') {
+            true => None,
+            false => Some(literal.into()),
+        };
+    }
+
+    let basic = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut chars = basic.chars();
+    let mut string = String::with_capacity(basic.len());
+
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '"' => return None,
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '"' => '"',
+                '\\' => '\\',
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    char::from_u32(u32::from_str_radix(&code, 16).ok()?)?
+                }
+                _ => return None,
+            },
+            c => c,
+        };
+
+        string.push(c);
+    }
+
+    Some(string)
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if matches!(c, '\\' | '"') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn number(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+fn substitute(template: &str, params: &[(&str, &str)]) -> Result<String> {
+    let mut query = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    let mut quoted = false;
+    let mut escaped = false;
+
+    while let Some(c) = chars.next() {
+        if c != '{' || escaped {
+            quoted ^= c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            query.push(c);
+            continue;
+        }
+
+        let param: String = chars.by_ref().take_while(|c| *c != '}').collect();
+        let value = params.iter().find(|(name, _)| *name == param);
+
+        let Some((_, value)) = value else {
+            let message = format!("Missing query parameter {param}");
+            return Err(Error::query(message));
+        };
+
+        match quoted || number(value) {
+            true => query.push_str(&escape(value)),
+            false => query.push_str(&format!("\"{}\"", escape(value))),
+        }
+    }
+
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryLibrary;
+    use crate::ast::Ast;
+
+    const LIBRARY: &str = r##"
+# Saved queries.
+[queries]
+long = "words > 100000" # Inline comment.
+hash = "title: \"#1\""
+'literal key' = 'title: C:\path'
+unicode = "title: caf\u00e9"
+search = "title:{title}"
+quoted = "title:\"{title}\""
+limited = "title: a limit {count}"
+"##;
+
+    fn library() -> QueryLibrary {
+        QueryLibrary::from_toml(LIBRARY).unwrap()
+    }
+
+    fn value(name: &str, params: &[(&str, &str)]) -> String {
+        match library().instantiate(name, params).unwrap().ast() {
+            Ast::Term(term) => term.value.clone(),
+            ast => panic!("Unexpected {ast:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_toml_subset() {
+        let library = library();
+
+        assert_eq!(library.len(), 7);
+        assert_eq!(library.get("long"), Some("words > 100000"));
+        assert_eq!(library.get("hash"), Some("title: \"#1\""));
+        assert_eq!(library.get("literal key"), Some("title: C:\\path"));
+        assert_eq!(library.get("unicode"), Some("title: caf\u{e9}"));
+        assert_eq!(library.params("search"), ["title"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_toml() {
+        let error = |text| QueryLibrary::from_toml(text).unwrap_err();
+
+        let other = error("[other]\na = \"b\"");
+        assert_eq!(other.context().line, Some(1));
+
+        let duplicate = error("[queries]\na = \"b\"\na = \"c\"");
+        assert_eq!(duplicate.context().line, Some(3));
+
+        assert!(QueryLibrary::from_toml("a = b").is_err());
+        assert!(QueryLibrary::from_toml("a = \"\\q\"").is_err());
+        assert!(QueryLibrary::from_toml("queries = \"a\"").is_err());
+    }
+
+    #[test]
+    fn quotes_substituted_values() {
+        let title = "\"x\" limit 5 sort by words";
+        let query = library().instantiate("search", &[("title", title)]);
+        let query = query.unwrap();
+
+        assert_eq!(query.limit(), None);
+        assert_eq!(query.sort(), None);
+        assert_eq!(value("search", &[("title", title)]), title);
+        assert_eq!(value("quoted", &[("title", "a\"b")]), "a\"b");
+        assert_eq!(value("search", &[("title", "a) | (b")]), "a) | (b");
+    }
+
+    #[test]
+    fn keeps_numbers_in_clauses() {
+        let library = library();
+        let query = library.instantiate("limited", &[("count", "5")]);
+
+        assert_eq!(query.unwrap().limit(), Some(5));
+
+        let count = [("count", "5 sort by words")];
+        let query = library.instantiate("limited", &count).unwrap();
+
+        assert_eq!(query.limit(), None);
+        assert_eq!(query.sort(), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Query limits.
+
+use std::time::Duration;
+
+use fimfareader::error::Error;
+use fimfareader::error::Result;
+
+use crate::ast::Ast;
+use crate::ast::Members;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub max_length: usize,
+    pub max_clauses: usize,
+    pub max_depth: usize,
+    pub max_regex_size: usize,
+    pub budget: Option<Duration>,
+    pub allow_files: bool,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_length: usize::MAX,
+            max_clauses: usize::MAX,
+            max_depth: 64,
+            max_regex_size: 1_048_576,
+            budget: None,
+            allow_files: false,
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    pub fn max_clauses(mut self, max_clauses: usize) -> Self {
+        self.max_clauses = max_clauses;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn max_regex_size(mut self, max_regex_size: usize) -> Self {
+        self.max_regex_size = max_regex_size;
+        self
+    }
+
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn allow_files(mut self, allow_files: bool) -> Self {
+        self.allow_files = allow_files;
+        self
+    }
+
+    pub(crate) fn check_length(&self, query: &str) -> Result<()> {
+        match query.len() > self.max_length {
+            true => Err(Error::query("Query is too long")),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_depth(&self, query: &str) -> Result<()> {
+        match depth(query) > self.max_depth {
+            true => Err(Error::query("Query is nested too deeply")),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_members(&self, members: &Members) -> Result<()> {
+        match (members, self.allow_files) {
+            (Members::File(_), false) => {
+                Err(Error::query("Story id files are not allowed"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_clauses(&self, ast: &Ast) -> Result<()> {
+        match clauses(ast) > self.max_clauses {
+            true => Err(Error::query("Query has too many clauses")),
+            false => Ok(()),
+        }
+    }
+}
+
+fn clauses(ast: &Ast) -> usize {
+    match ast {
+        Ast::Term(_) | Ast::Has(_) | Ast::In(_) => 1,
+        Ast::Sequel(_) | Ast::Compare(_) => 1,
+        Ast::Not(ast) => clauses(ast),
+        Ast::And(asts) | Ast::Or(asts) => asts.iter().map(clauses).sum(),
+    }
+}
+
+fn depth(query: &str) -> usize {
+    let mut chars = query.chars();
+    let mut quoted = false;
+    let mut depth = 0usize;
+    let mut deepest = 0;
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (_, '\\') => {
+                chars.next();
+            }
+            (_, '"') => quoted = !quoted,
+            (false, '(') => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            (false, ')') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    deepest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Limits;
+    use crate::parser::parse;
+    use crate::parser::parse_with;
+
+    fn nested(depth: usize) -> String {
+        format!("{}title: a{}", "(".repeat(depth), ")".repeat(depth))
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let limits = Limits::new().max_clauses(10);
+
+        assert!(parse_with(&nested(5000), &limits).is_err());
+        assert!(parse(&nested(5000)).is_err());
+    }
+
+    #[test]
+    fn accepts_nesting_within_limit() {
+        let limits = Limits::default();
+
+        assert!(parse(&nested(limits.max_depth)).is_ok());
+        assert!(parse(&nested(limits.max_depth + 1)).is_err());
+    }
+
+    #[test]
+    fn rejects_files_by_default() {
+        let path = std::env::temp_dir().join("fimfareader-limits-ids.txt");
+        std::fs::write(&path, "1\n2\n").unwrap();
+
+        let query = format!("id in file({})", path.display());
+        let limits = Limits::new().allow_files(true);
+
+        assert!(parse(&query).is_err());
+        assert!(parse_with(&query, &limits).is_ok());
+    }
+
+    #[test]
+    fn ignores_quoted_parens() {
+        let query = format!("title: \"{}\"", "(".repeat(100));
+
+        assert!(parse(&query).is_ok());
+    }
+}
@@ -1,18 +1,46 @@
 //! Query optimizer.
 
+use std::borrow::Cow;
+use std::str::FromStr;
+
 use chrono::prelude::*;
 use dateparser::parse_with_timezone;
 use regex::escape;
+use regex::Error as RegexError;
+use regex::Regex;
 use regex::RegexBuilder;
 
+use fimfareader::archive::Lookup;
+use fimfareader::archive::Story;
+use fimfareader::archive::StoryId;
+use fimfareader::archive::Tag;
+use fimfareader::archive::TagKind;
 use fimfareader::error::Error;
 use fimfareader::error::Result;
+use fimfareader::unicode::compose;
+use fimfareader::unicode::fold;
 
+use crate::ast::Case;
+use crate::ast::Op;
+use crate::ast::Term;
+use crate::distance::threshold;
+use crate::distance::within;
+use crate::parser::ChapterSource;
 use crate::parser::DateOpt;
 use crate::parser::Field;
-use crate::parser::Filter;
-use crate::parser::Op;
+use crate::parser::Predicate;
+use crate::parser::Quantifier;
 use crate::parser::Source;
+use crate::parser::TextField;
+
+type Number = Box<dyn Fn(&Story) -> Option<f64> + Send + Sync>;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Flags {
+    pub case: Option<Case>,
+    pub fold: bool,
+    pub size: usize,
+}
 
 macro_rules! ok {
     ($func:expr) => {
@@ -20,47 +48,490 @@ macro_rules! ok {
     };
 }
 
-pub fn optimize(src: Source, op: Op, value: &str) -> Result<Filter> {
+pub fn optimize(
+    src: Source,
+    op: Op,
+    flags: Flags,
+    value: &str,
+) -> Result<Predicate> {
+    let ranged = src.is_numeric();
+
+    match (&op, value.split_once("..")) {
+        (Op::Exact, Some((start, end))) if ranged => {
+            range(start, end, |op, value| {
+                single(src.clone(), op, flags, value)
+            })
+        }
+        _ => single(src, op, flags, value),
+    }
+}
+
+pub(crate) fn lookup(term: &Term) -> Option<Lookup> {
+    if term.op != Op::Exact || term.value.contains("..") {
+        return None;
+    }
+
+    match (term.field.as_str(), &term.source) {
+        ("id", Source::Id(_)) => term.value.parse().ok().map(Lookup::Story),
+        ("author id", Source::Int(_)) => {
+            term.value.parse().ok().map(Lookup::Author)
+        }
+        (_, Source::Tag(None)) => {
+            Some(Lookup::Tag(term.value.as_str().into()))
+        }
+        (_, Source::Tags) if count(&term.value).is_none() => {
+            Some(Lookup::Tag(term.value.as_str().into()))
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn cost(src: &Source, op: Op) -> u32 {
+    let text = |op| match op {
+        Op::Approximate(_) => 32,
+        Op::Fuzzy => 16,
+        _ => 4,
+    };
+
     match src {
-        Source::Str(f) => str(f, op, value),
+        Source::Id(_) | Source::Int(_) | Source::IntOpt(_) => 1,
+        Source::Rating(_) | Source::Completion(_) | Source::Bool(_) => 1,
+        Source::Dto(_) | Source::Published | Source::Metric(_) => 2,
+        Source::Str(_) => text(op),
+        Source::Tag(_) | Source::Tags => 2 * text(op),
+        Source::Chapter(_, ChapterSource::Str(_)) => 8 * text(op),
+        Source::Chapter(_, _) => 16,
+    }
+}
+
+fn single(
+    src: Source,
+    op: Op,
+    flags: Flags,
+    value: &str,
+) -> Result<Predicate> {
+    match src {
+        Source::Str(f) => text(f, op, flags, value),
+        Source::Id(f) => id(f, op, value),
         Source::Int(f) => int(f, op, value),
+        Source::IntOpt(f) => iopt(f, op, value),
         Source::Dto(f) => dto(f, op, value),
+        Source::Rating(f) => ordinal(f, op, value),
+        Source::Completion(f) => ordinal(f, op, value),
+        Source::Bool(f) => flag(f, op, value),
+        Source::Published => match boolean(value) {
+            Some(_) => flag(&|s| &s.published, op, value),
+            None => dto(&|s| &s.date_published, op, value),
+        },
+        Source::Tag(kind) => tag(kind, op, flags, value),
+        Source::Tags => match count(value) {
+            Some(_) => metric(tags, op, value),
+            None => tag(None, op, flags, value),
+        },
+        Source::Metric(f) => metric(f, op, value),
+        Source::Chapter(quantifier, f) => {
+            chapter(quantifier, f, op, flags, value)
+        }
+    }
+}
+
+fn chapter(
+    quantifier: Quantifier,
+    src: ChapterSource,
+    op: Op,
+    flags: Flags,
+    value: &str,
+) -> Result<Predicate> {
+    let ranged = matches!(src, ChapterSource::Int(_) | ChapterSource::Dto(_));
+
+    let single = |src, op, value: &str| match src {
+        ChapterSource::Int(f) => int(f, op, value),
+        ChapterSource::Str(f) => text(f, op, flags, value),
+        ChapterSource::Dto(f) => dto(f, op, value),
+    };
+
+    let filter = match (&op, value.split_once("..")) {
+        (Op::Exact, Some((start, end))) if ranged => {
+            range(start, end, |op, value| single(src.clone(), op, value))?
+        }
+        _ => single(src, op, value)?,
+    };
+
+    match quantifier {
+        Quantifier::Any => {
+            ok!(move |s: &Story| s.chapters.iter().any(&filter))
+        }
+        Quantifier::All => {
+            ok!(move |s: &Story| s.chapters.iter().all(&filter))
+        }
+    }
+}
+
+fn range<R, F>(start: &str, end: &str, single: F) -> Result<Predicate<R>>
+where
+    R: 'static,
+    F: Fn(Op, &str) -> Result<Predicate<R>>,
+{
+    let bound = |op, value: &str| match value.trim() {
+        "" => Ok(None),
+        value => single(op, value).map(Some),
+    };
+
+    let lower = bound(Op::AtLeast, start)?;
+    let upper = bound(Op::LessThan, end)?;
+
+    match (lower, upper) {
+        (Some(lower), Some(upper)) => ok!(move |s| lower(s) && upper(s)),
+        (Some(lower), None) => Ok(lower),
+        (None, Some(upper)) => Ok(upper),
+        (None, None) => Err(Error::query("Invalid value for range")),
     }
 }
 
-fn str(f: Field<Box<str>>, op: Op, value: &str) -> Result<Filter> {
-    let exact: Box<str> = value.into();
+fn normalize(value: &str, fold: bool) -> Cow<'_, str> {
+    match fold {
+        true => self::fold(value),
+        false => compose(value),
+    }
+}
 
+fn caseless(a: &str, b: &str) -> bool {
+    let a = a.chars().flat_map(char::to_lowercase);
+    let b = b.chars().flat_map(char::to_lowercase);
+
+    a.eq(b)
+}
+
+fn fuzzy(value: &str, case: Case, size: usize) -> Result<Regex> {
     let result = RegexBuilder::new(&escape(value))
-        .case_insensitive(true)
-        .size_limit(1_048_576)
+        .case_insensitive(case == Case::Insensitive)
+        .size_limit(size)
         .build();
 
-    let Ok(regex) = result else {
-        return Err(Error::query("Invalid value for fuzzy match"));
+    match result {
+        Ok(regex) => Ok(regex),
+        Err(RegexError::CompiledTooBig(_)) => {
+            Err(Error::query("Fuzzy match exceeds size limit"))
+        }
+        Err(_) => Err(Error::query("Invalid value for fuzzy match")),
+    }
+}
+
+fn approximate(
+    value: &str,
+    limit: Option<usize>,
+    case: Option<Case>,
+) -> impl Fn(&str) -> bool + Send + Sync {
+    let sensitive = case == Some(Case::Sensitive);
+    let limit = limit.unwrap_or_else(|| threshold(value));
+
+    let target = match sensitive {
+        true => value.to_string(),
+        false => value.to_lowercase(),
     };
 
-    match op {
-        Op::Exact => ok!(move |s| *f(s) == exact),
-        Op::Fuzzy => ok!(move |s| regex.is_match(f(s))),
+    move |value| match sensitive {
+        true => within(value, &target, limit),
+        false => within(&value.to_lowercase(), &target, limit),
+    }
+}
+
+fn text<R: 'static>(
+    f: TextField<R>,
+    op: Op,
+    flags: Flags,
+    value: &str,
+) -> Result<Predicate<R>> {
+    match f {
+        TextField::Boxed(f) => str(f, op, flags, value),
+        TextField::Shared(f) => str(f, op, flags, value),
+    }
+}
+
+fn str<T, R>(
+    f: Field<T, R>,
+    op: Op,
+    flags: Flags,
+    value: &str,
+) -> Result<Predicate<R>>
+where
+    T: AsRef<str> + 'static,
+    R: 'static,
+{
+    let fold = flags.fold;
+    let exact = normalize(value, fold).into_owned();
+
+    match (op, flags.case) {
+        (Op::Exact, Some(Case::Insensitive)) => {
+            ok!(move |s| caseless(&normalize(f(s).as_ref(), fold), &exact))
+        }
+        (Op::NotEqual, Some(Case::Insensitive)) => {
+            ok!(move |s| !caseless(&normalize(f(s).as_ref(), fold), &exact))
+        }
+        (Op::Exact, _) => {
+            ok!(move |s| normalize(f(s).as_ref(), fold) == *exact)
+        }
+        (Op::NotEqual, _) => {
+            ok!(move |s| normalize(f(s).as_ref(), fold) != *exact)
+        }
+        (Op::Fuzzy, case) => {
+            let case = case.unwrap_or(Case::Insensitive);
+            let regex = fuzzy(&exact, case, flags.size)?;
+            ok!(move |s| regex.is_match(&normalize(f(s).as_ref(), fold)))
+        }
+        (Op::Approximate(limit), case) => {
+            let near = approximate(&exact, limit, case);
+            ok!(move |s| near(&normalize(f(s).as_ref(), fold)))
+        }
         _ => Err(Error::query("Invalid operation for text type")),
     }
 }
 
-fn int(f: Field<i32>, op: Op, value: &str) -> Result<Filter> {
-    let Ok(value) = value.parse() else {
+fn tag(
+    kind: Option<TagKind>,
+    op: Op,
+    flags: Flags,
+    value: &str,
+) -> Result<Predicate> {
+    let negate = matches!(op, Op::NotEqual);
+    let fold = flags.fold;
+    let exact = normalize(value, fold).into_owned();
+
+    let name: Box<dyn Fn(&str) -> bool + Send + Sync> = match (op, flags.case)
+    {
+        (Op::Exact | Op::NotEqual, Some(Case::Sensitive)) => {
+            Box::new(move |name| normalize(name, fold) == *exact)
+        }
+        (Op::Exact | Op::NotEqual, _) => {
+            Box::new(move |name| caseless(&normalize(name, fold), &exact))
+        }
+        (Op::Fuzzy, case) => {
+            let case = case.unwrap_or(Case::Insensitive);
+            let regex = fuzzy(&exact, case, flags.size)?;
+            Box::new(move |name| regex.is_match(&normalize(name, fold)))
+        }
+        (Op::Approximate(limit), case) => {
+            let near = approximate(&exact, limit, case);
+            Box::new(move |name| near(&normalize(name, fold)))
+        }
+        _ => return Err(Error::query("Invalid operation for tag type")),
+    };
+
+    ok!(move |s| {
+        let mut tags = s.tags.iter();
+        let kind = |t: &Tag| kind.as_ref().is_none_or(|k| t.r#type == *k);
+
+        tags.any(|t| kind(t) && name(&t.name)) != negate
+    })
+}
+
+fn id(f: Field<StoryId>, op: Op, value: &str) -> Result<Predicate> {
+    let Ok(value) = value.parse::<StoryId>() else {
+        return Err(Error::query("Invalid value for story ID"));
+    };
+
+    match op {
+        Op::Exact => ok!(move |s| *f(s) == value),
+        Op::NotEqual => ok!(move |s| *f(s) != value),
+        Op::Fuzzy => ok!(move |s| *f(s) == value),
+        Op::LessThan => ok!(move |s| *f(s) < value),
+        Op::AtMost => ok!(move |s| *f(s) <= value),
+        Op::MoreThan => ok!(move |s| *f(s) > value),
+        Op::AtLeast => ok!(move |s| *f(s) >= value),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for story ID"))
+        }
+    }
+}
+
+fn scaled(value: &str) -> Option<f64> {
+    let value = value.trim().to_lowercase();
+
+    let (value, scale) = match value.as_bytes().last() {
+        Some(b'k') => (&value[..value.len() - 1], 1e3),
+        Some(b'm') => (&value[..value.len() - 1], 1e6),
+        _ => (value.as_str(), 1.0),
+    };
+
+    Some(value.parse::<f64>().ok()? * scale)
+}
+
+fn count(value: &str) -> Option<i32> {
+    if let Ok(value) = value.trim().parse() {
+        return Some(value);
+    }
+
+    let scaled = scaled(value)?;
+
+    match scaled.fract() == 0.0 && scaled.abs() <= i32::MAX as f64 {
+        true => Some(scaled as i32),
+        false => None,
+    }
+}
+
+fn metric(
+    f: fn(&Story) -> Option<f64>,
+    op: Op,
+    value: &str,
+) -> Result<Predicate> {
+    let Some(value) = scaled(value) else {
+        return Err(Error::query("Invalid value for decimal type"));
+    };
+
+    match op {
+        Op::Exact | Op::Fuzzy => ok!(move |s| f(s) == Some(value)),
+        Op::NotEqual => ok!(move |s| f(s) != Some(value)),
+        Op::LessThan => ok!(move |s| f(s).is_some_and(|n| n < value)),
+        Op::AtMost => ok!(move |s| f(s).is_some_and(|n| n <= value)),
+        Op::MoreThan => ok!(move |s| f(s).is_some_and(|n| n > value)),
+        Op::AtLeast => ok!(move |s| f(s).is_some_and(|n| n >= value)),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for decimal type"))
+        }
+    }
+}
+
+fn number(src: Source) -> Option<Number> {
+    let number: Number = match src {
+        Source::Id(f) => Box::new(move |s| Some(f64::from(f(s).0))),
+        Source::Int(f) => Box::new(move |s| Some(f64::from(*f(s)))),
+        Source::IntOpt(f) => Box::new(move |s| f(s).map(f64::from)),
+        Source::Dto(f) => Box::new(move |s| f(s).map(timestamp)),
+        Source::Published => Box::new(|s| s.date_published.map(timestamp)),
+        Source::Metric(f) => Box::new(f),
+        Source::Tags => Box::new(tags),
+        _ => return None,
+    };
+
+    Some(number)
+}
+
+fn tags(story: &Story) -> Option<f64> {
+    Some(story.tags.len() as f64)
+}
+
+fn timestamp(date: DateTime<Utc>) -> f64 {
+    date.timestamp() as f64
+}
+
+pub(crate) fn versus(
+    left: Source,
+    op: Op,
+    right: Source,
+) -> Result<Predicate> {
+    let (Some(a), Some(b)) = (number(left), number(right)) else {
+        return Err(Error::query("Invalid source for comparison"));
+    };
+
+    let pair = move |s: &Story| Some((a(s)?, b(s)?));
+
+    match op {
+        Op::Exact | Op::Fuzzy => {
+            ok!(move |s| pair(s).is_some_and(|(a, b)| a == b))
+        }
+        Op::NotEqual => ok!(move |s| pair(s).is_some_and(|(a, b)| a != b)),
+        Op::LessThan => ok!(move |s| pair(s).is_some_and(|(a, b)| a < b)),
+        Op::AtMost => ok!(move |s| pair(s).is_some_and(|(a, b)| a <= b)),
+        Op::MoreThan => ok!(move |s| pair(s).is_some_and(|(a, b)| a > b)),
+        Op::AtLeast => ok!(move |s| pair(s).is_some_and(|(a, b)| a >= b)),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for comparison"))
+        }
+    }
+}
+
+fn int<R: 'static>(
+    f: Field<i32, R>,
+    op: Op,
+    value: &str,
+) -> Result<Predicate<R>> {
+    let Some(value) = count(value) else {
         return Err(Error::query("Invalid value for number type"));
     };
 
     match op {
         Op::Exact => ok!(move |s| *f(s) == value),
+        Op::NotEqual => ok!(move |s| *f(s) != value),
         Op::Fuzzy => ok!(move |s| *f(s) == value),
         Op::LessThan => ok!(move |s| *f(s) < value),
+        Op::AtMost => ok!(move |s| *f(s) <= value),
         Op::MoreThan => ok!(move |s| *f(s) > value),
+        Op::AtLeast => ok!(move |s| *f(s) >= value),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for number type"))
+        }
+    }
+}
+
+fn ordinal<T>(f: Field<T>, op: Op, value: &str) -> Result<Predicate>
+where
+    T: FromStr<Err = Error> + Ord + Send + Sync + 'static,
+{
+    let value: T = value.parse()?;
+
+    match op {
+        Op::Exact => ok!(move |s| *f(s) == value),
+        Op::NotEqual => ok!(move |s| *f(s) != value),
+        Op::Fuzzy => ok!(move |s| *f(s) == value),
+        Op::LessThan => ok!(move |s| *f(s) < value),
+        Op::AtMost => ok!(move |s| *f(s) <= value),
+        Op::MoreThan => ok!(move |s| *f(s) > value),
+        Op::AtLeast => ok!(move |s| *f(s) >= value),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for ordered type"))
+        }
+    }
+}
+
+fn iopt(f: Field<Option<i32>>, op: Op, value: &str) -> Result<Predicate> {
+    let Some(value) = count(value) else {
+        return Err(Error::query("Invalid value for number type"));
+    };
+
+    match op {
+        Op::Exact => ok!(move |s| *f(s) == Some(value)),
+        Op::NotEqual => ok!(move |s| *f(s) != Some(value)),
+        Op::Fuzzy => ok!(move |s| *f(s) == Some(value)),
+        Op::LessThan => ok!(move |s| f(s).is_some_and(|n| n < value)),
+        Op::AtMost => ok!(move |s| f(s).is_some_and(|n| n <= value)),
+        Op::MoreThan => ok!(move |s| f(s).is_some_and(|n| n > value)),
+        Op::AtLeast => ok!(move |s| f(s).is_some_and(|n| n >= value)),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for number type"))
+        }
     }
 }
 
-fn dto(f: Field<DateOpt>, op: Op, value: &str) -> Result<Filter> {
+fn boolean(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn flag(f: Field<bool>, op: Op, value: &str) -> Result<Predicate> {
+    let Some(value) = boolean(value) else {
+        return Err(Error::query("Invalid value for boolean type"));
+    };
+
+    match op {
+        Op::Exact => ok!(move |s| *f(s) == value),
+        Op::NotEqual => ok!(move |s| *f(s) != value),
+        Op::Fuzzy => ok!(move |s| *f(s) == value),
+        _ => Err(Error::query("Invalid operation for boolean type")),
+    }
+}
+
+fn dto<R: 'static>(
+    f: Field<DateOpt, R>,
+    op: Op,
+    value: &str,
+) -> Result<Predicate<R>> {
+    if let Some((start, end)) = period(value) {
+        return span(f, op, start, end);
+    }
+
     let Ok(value) = parse_with_timezone(value, &Local) else {
         return Err(Error::query("Invalid value for date type"));
     };
@@ -70,6 +541,10 @@ fn dto(f: Field<DateOpt>, op: Op, value: &str) -> Result<Filter> {
             Some(dt) => *dt == value,
             None => false,
         }),
+        Op::NotEqual => ok!(move |s| match f(s) {
+            Some(dt) => *dt != value,
+            None => true,
+        }),
         Op::Fuzzy => ok!(move |s| match f(s) {
             Some(dt) => dt.date_naive() == value.date_naive(),
             None => false,
@@ -78,9 +553,164 @@ fn dto(f: Field<DateOpt>, op: Op, value: &str) -> Result<Filter> {
             Some(dt) => *dt < value,
             None => false,
         }),
+        Op::AtMost => ok!(move |s| match f(s) {
+            Some(dt) => *dt <= value,
+            None => false,
+        }),
         Op::MoreThan => ok!(move |s| match f(s) {
             Some(dt) => *dt > value,
             None => false,
         }),
+        Op::AtLeast => ok!(move |s| match f(s) {
+            Some(dt) => *dt >= value,
+            None => false,
+        }),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for date type"))
+        }
+    }
+}
+
+fn period(value: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let digits = |part: &str, len| {
+        part.len() == len && part.bytes().all(|b| b.is_ascii_digit())
+    };
+
+    let (year, month) = match value.trim().split_once('-') {
+        Some((year, month)) if digits(year, 4) && digits(month, 2) => {
+            (year.parse().ok()?, Some(month.parse().ok()?))
+        }
+        None if digits(value.trim(), 4) => (value.trim().parse().ok()?, None),
+        _ => return None,
+    };
+
+    let (start, end) = match month {
+        None => ((year, 1), (year + 1, 1)),
+        Some(12) => ((year, 12), (year + 1, 1)),
+        Some(month) => ((year, month), (year, month + 1)),
+    };
+
+    let local = |(year, month): (i32, u32)| {
+        let start = Local.with_ymd_and_hms(year, month, 1, 0, 0, 0);
+        start.earliest().map(|dt| dt.with_timezone(&Utc))
+    };
+
+    Some((local(start)?, local(end)?))
+}
+
+fn span<R: 'static>(
+    f: Field<DateOpt, R>,
+    op: Op,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Predicate<R>> {
+    match op {
+        Op::Exact | Op::Fuzzy => ok!(move |s| match f(s) {
+            Some(dt) => start <= *dt && *dt < end,
+            None => false,
+        }),
+        Op::NotEqual => ok!(move |s| match f(s) {
+            Some(dt) => *dt < start || end <= *dt,
+            None => true,
+        }),
+        Op::LessThan => ok!(move |s| match f(s) {
+            Some(dt) => *dt < start,
+            None => false,
+        }),
+        Op::AtMost => ok!(move |s| match f(s) {
+            Some(dt) => *dt < end,
+            None => false,
+        }),
+        Op::MoreThan => ok!(move |s| match f(s) {
+            Some(dt) => *dt >= end,
+            None => false,
+        }),
+        Op::AtLeast => ok!(move |s| match f(s) {
+            Some(dt) => *dt >= start,
+            None => false,
+        }),
+        Op::Approximate(_) => {
+            Err(Error::query("Invalid operation for date type"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(op: Op, value: &str) -> Result<Predicate<i32>> {
+        let Ok(value) = value.parse::<i32>() else {
+            return Err(Error::query("Invalid value for integer type"));
+        };
+
+        match op {
+            Op::AtLeast => ok!(move |n: &i32| *n >= value),
+            Op::LessThan => ok!(move |n: &i32| *n < value),
+            op => panic!("Unexpected {op}"),
+        }
+    }
+
+    fn matches(start: &str, end: &str) -> Vec<i32> {
+        let predicate = range(start, end, int).unwrap();
+
+        (0..6).filter(|n| predicate(n)).collect()
+    }
+
+    #[test]
+    fn matches_half_open_ranges() {
+        assert_eq!(matches("1", "4"), [1, 2, 3]);
+        assert_eq!(matches("", "2"), [0, 1]);
+        assert_eq!(matches("4", ""), [4, 5]);
+        assert_eq!(matches("3", "3"), [] as [i32; 0]);
+    }
+
+    #[test]
+    fn rejects_empty_ranges() {
+        assert!(range("", "", int).is_err());
+        assert!(range("a", "3", int).is_err());
+    }
+
+    fn local(year: i32, month: u32, day: u32) -> DateOpt {
+        let date = Local.with_ymd_and_hms(year, month, day, 0, 0, 0);
+        date.earliest().map(|dt| dt.with_timezone(&Utc))
+    }
+
+    #[test]
+    fn parses_periods() {
+        let year = period("2020").unwrap();
+        assert_eq!(Some(year.0), local(2020, 1, 1));
+        assert_eq!(Some(year.1), local(2021, 1, 1));
+
+        let month = period("2020-12").unwrap();
+        assert_eq!(Some(month.0), local(2020, 12, 1));
+        assert_eq!(Some(month.1), local(2021, 1, 1));
+
+        assert!(period("20").is_none());
+        assert!(period("2020-1").is_none());
+        assert!(period("2020-13").is_none());
+        assert!(period("2020-01-01").is_none());
+    }
+
+    #[test]
+    fn compares_against_periods() {
+        let f: Field<DateOpt, DateOpt> = &|dt| dt;
+        let check =
+            |op, value: &str, dt: DateOpt| dto(f, op, value).unwrap()(&dt);
+
+        let inside = local(2020, 12, 31);
+        let after = local(2021, 1, 1);
+        let before = local(2020, 11, 30);
+
+        assert!(check(Op::Exact, "2020-12", inside));
+        assert!(!check(Op::Exact, "2020-12", after));
+        assert!(check(Op::AtMost, "2020-12", inside));
+        assert!(!check(Op::AtMost, "2020-12", after));
+        assert!(check(Op::MoreThan, "2020-12", after));
+        assert!(!check(Op::MoreThan, "2020-12", inside));
+        assert!(check(Op::LessThan, "2020-12", before));
+        assert!(!check(Op::LessThan, "2020-12", inside));
+        assert!(check(Op::NotEqual, "2020-12", None));
+        assert!(!check(Op::Exact, "2020", None));
     }
 }
@@ -1,5 +1,8 @@
 //! Query parser.
 
+use std::mem::replace;
+use std::sync::Arc;
+
 use chrono::DateTime;
 use chrono::Utc;
 use derive_more::From;
@@ -8,45 +11,195 @@ use nom::branch::alt;
 use nom::bytes::complete::escaped;
 use nom::bytes::complete::tag;
 use nom::character::complete::char;
+use nom::character::complete::digit1;
 use nom::character::complete::none_of;
 use nom::character::complete::one_of;
 use nom::character::complete::space0;
+use nom::character::complete::space1;
+use nom::combinator::consumed;
 use nom::combinator::eof;
 use nom::combinator::map;
+use nom::combinator::map_res;
+use nom::combinator::not;
+use nom::combinator::opt;
+use nom::combinator::peek;
+use nom::combinator::recognize;
 use nom::combinator::value;
-use nom::error::Error as NomError;
-use nom::error::ErrorKind as NomErrorKind;
+use nom::combinator::verify;
+use nom::multi::fold_many1;
 use nom::multi::separated_list1;
 use nom::sequence::delimited;
+use nom::sequence::pair;
 use nom::sequence::preceded;
 use nom::sequence::terminated;
 use nom::sequence::tuple;
-use nom::Err as NomErr;
 use nom::Finish;
 use nom::IResult;
 
+use fimfareader::archive::Chapter;
+use fimfareader::archive::CompletionStatus;
+use fimfareader::archive::ContentRating;
+use fimfareader::archive::Order;
+use fimfareader::archive::SortKey;
+use fimfareader::archive::Status;
 use fimfareader::archive::Story;
+use fimfareader::archive::StoryId;
+use fimfareader::archive::TagKind;
 use fimfareader::error::*;
 
-use crate::optimizer::optimize;
+use crate::ast::Ast;
+use crate::ast::Case;
+use crate::ast::Compare;
+use crate::ast::Has;
+use crate::ast::Members;
+use crate::ast::Op;
+use crate::ast::Sequel;
+use crate::ast::Term;
+use crate::builder::Field as Named;
+use crate::builder::QueryBuilder;
+use crate::diagnostic::diagnose;
+use crate::limits::Limits;
+use crate::query::Query;
+
+pub(crate) const FIELDS: &[&str] = &[
+    "id",
+    "url",
+    "story",
+    "title",
+    "description",
+    "short description",
+    "modified",
+    "updated",
+    "published",
+    "visible",
+    "submitted",
+    "chapters",
+    "comments",
+    "dislikes",
+    "likes",
+    "total views",
+    "views",
+    "words",
+    "like ratio",
+    "wilson score",
+    "views per word",
+    "max chapter words",
+    "min chapter words",
+    "avg chapter words",
+    "content rating",
+    "rating",
+    "completion status",
+    "completion",
+    "author",
+    "author name",
+    "author id",
+    "author joined",
+    "author followers",
+    "author stories",
+    "author blog posts",
+    "path",
+    "archive path",
+    "entry checked",
+    "entry created",
+    "entry fetched",
+    "entry updated",
+    "tag",
+    "tags",
+    "character",
+    "genre",
+    "series",
+    "warning",
+    "chapter id",
+    "chapter number",
+    "chapter title",
+    "chapter url",
+    "chapter words",
+    "chapter views",
+    "chapter published",
+    "chapter modified",
+];
+
+pub(crate) const OPERATORS: &[&str] =
+    &[":", "=", "!=", "<", "<=", ">", ">=", "~="];
 
 pub(crate) type DateOpt = Option<DateTime<Utc>>;
-pub(crate) type Field<T> = &'static (dyn Fn(&Story) -> &T + Sync);
-pub(crate) type Filter = Box<dyn Fn(&Story) -> bool + Sync>;
+pub(crate) type Field<T, R = Story> = &'static (dyn Fn(&R) -> &T + Sync);
+pub(crate) type Predicate<R = Story> = Box<dyn Fn(&R) -> bool + Send + Sync>;
+
+#[derive(Clone, Copy, From)]
+pub(crate) enum TextField<R: 'static = Story> {
+    Boxed(Field<Box<str>, R>),
+    Shared(Field<Arc<str>, R>),
+}
 
-#[derive(From)]
+#[derive(Clone, From)]
 pub(crate) enum Source {
+    Id(Field<StoryId>),
     Int(Field<i32>),
-    Str(Field<Box<str>>),
+    IntOpt(Field<Option<i32>>),
+    #[from(ignore)]
+    Str(TextField),
     Dto(Field<DateOpt>),
+    Rating(Field<ContentRating>),
+    Completion(Field<CompletionStatus>),
+    Bool(Field<bool>),
+    #[from(ignore)]
+    Published,
+    #[from(ignore)]
+    Tag(Option<TagKind>),
+    #[from(ignore)]
+    Tags,
+    #[from(ignore)]
+    Chapter(Quantifier, ChapterSource),
+    #[from(ignore)]
+    Metric(fn(&Story) -> Option<f64>),
+}
+
+impl Source {
+    pub(crate) fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Source::Id(_)
+                | Source::Int(_)
+                | Source::IntOpt(_)
+                | Source::Dto(_)
+                | Source::Published
+                | Source::Metric(_)
+                | Source::Tags
+        )
+    }
 }
 
-#[derive(Clone)]
-pub(crate) enum Op {
-    Exact,
-    Fuzzy,
-    LessThan,
-    MoreThan,
+#[derive(Clone, From)]
+pub(crate) enum ChapterSource {
+    Int(Field<i32, Chapter>),
+    #[from(ignore)]
+    Str(TextField<Chapter>),
+    Dto(Field<DateOpt, Chapter>),
+}
+
+macro_rules! text {
+    ($($source:ident<$record:ty>),+) => {$(
+        impl From<Field<Box<str>, $record>> for $source {
+            fn from(field: Field<Box<str>, $record>) -> Self {
+                $source::Str(TextField::from(field))
+            }
+        }
+
+        impl From<Field<Arc<str>, $record>> for $source {
+            fn from(field: Field<Arc<str>, $record>) -> Self {
+                $source::Str(TextField::from(field))
+            }
+        }
+    )+};
+}
+
+text!(Source<Story>, ChapterSource<Chapter>);
+
+#[derive(Clone, Copy)]
+pub(crate) enum Quantifier {
+    Any,
+    All,
 }
 
 macro_rules! ext {
@@ -58,7 +211,52 @@ macro_rules! ext {
     };
 }
 
-fn source(input: &str) -> IResult<&str, Source> {
+macro_rules! chx {
+    ($($tag:literal => $path:ident),+,) => {
+        alt(($(preceded(tag($tag), |input| {
+            let field: Field<_, Chapter> = &|chapter| &chapter.$path;
+            Ok((input, ChapterSource::from(field)))
+        })),+))
+    };
+}
+
+fn chapter(input: &str) -> IResult<&str, Source> {
+    let quantifier = alt((
+        value(Quantifier::All, pair(tag("all"), space1)),
+        value(Quantifier::Any, pair(tag("any"), space1)),
+    ));
+
+    let field = chx! {
+        "chapter id" => id,
+        "chapter number" => chapter_number,
+        "chapter title" => title,
+        "chapter url" => url,
+        "chapter words" => num_words,
+        "chapter views" => num_views,
+        "chapter published" => date_published,
+        "chapter modified" => date_modified,
+    };
+
+    let source = pair(opt(quantifier), field);
+
+    map(source, |(quantifier, field)| {
+        Source::Chapter(quantifier.unwrap_or(Quantifier::Any), field)
+    })(input)
+}
+
+fn wilson_score(story: &Story) -> Option<f64> {
+    Some(story.wilson_score())
+}
+
+fn max_chapter_words(story: &Story) -> Option<f64> {
+    story.max_chapter_words().map(f64::from)
+}
+
+fn min_chapter_words(story: &Story) -> Option<f64> {
+    story.min_chapter_words().map(f64::from)
+}
+
+pub(crate) fn source(input: &str) -> IResult<&str, Source> {
     let story = ext! {
         "id" => id,
         "url" => url,
@@ -67,7 +265,6 @@ fn source(input: &str) -> IResult<&str, Source> {
         "description" => description_html,
         "short description" => short_description,
         "modified" => date_modified,
-        "published" => date_published,
         "updated" => date_updated,
         "chapters" => num_chapters,
         "comments" => num_comments,
@@ -76,13 +273,21 @@ fn source(input: &str) -> IResult<&str, Source> {
         "total views" => total_num_views,
         "views" => num_views,
         "words" => num_words,
+        "content rating" => content_rating,
+        "rating" => content_rating,
+        "completion status" => completion_status,
+        "completion" => completion_status,
+        "submitted" => submitted,
     };
 
     let author = ext! {
-        "author" => author.name,
         "author name" => author.name,
         "author id" => author.id,
         "author joined" => author.date_joined,
+        "author followers" => author.num_followers,
+        "author stories" => author.num_stories,
+        "author blog posts" => author.num_blog_posts,
+        "author" => author.name,
     };
 
     let archive = ext! {
@@ -95,11 +300,163 @@ fn source(input: &str) -> IResult<&str, Source> {
         "entry updated" => archive.date_updated,
     };
 
-    preceded(space0, alt((story, author, archive)))(input)
+    let flags = alt((
+        value(Source::Published, tag("published")),
+        value(Source::Bool(&visible), tag("visible")),
+    ));
+
+    let tags = alt((
+        value(Source::Tags, tag("tags")),
+        value(Source::Tag(None), tag("tag")),
+        value(Source::Tag(Some(TagKind::Character)), tag("character")),
+        value(Source::Tag(Some(TagKind::Genre)), tag("genre")),
+        value(Source::Tag(Some(TagKind::Series)), tag("series")),
+        value(Source::Tag(Some(TagKind::Warning)), tag("warning")),
+    ));
+
+    let metrics = alt((
+        value(Source::Metric(Story::like_ratio), tag("like ratio")),
+        value(Source::Metric(wilson_score), tag("wilson score")),
+        value(Source::Metric(Story::views_per_word), tag("views per word")),
+        value(Source::Metric(max_chapter_words), tag("max chapter words")),
+        value(Source::Metric(min_chapter_words), tag("min chapter words")),
+        value(
+            Source::Metric(Story::avg_chapter_words),
+            tag("avg chapter words"),
+        ),
+    ));
+
+    let sources = alt((metrics, flags, chapter, story, author, archive, tags));
+
+    preceded(space0, sources)(input)
 }
 
-fn operator(input: &str) -> IResult<&str, Op> {
+macro_rules! get {
+    ($($path:ident).+) => {{
+        let field: Field<_> = &|story| &story.$($path).+;
+        Source::from(field)
+    }};
+}
+
+pub(crate) fn named(name: Named) -> Source {
+    match name {
+        Named::Id => get!(id),
+        Named::Url => get!(url),
+        Named::Title => get!(title),
+        Named::Description => get!(description_html),
+        Named::ShortDescription => get!(short_description),
+        Named::Modified => get!(date_modified),
+        Named::Updated => get!(date_updated),
+        Named::Published => Source::Published,
+        Named::Chapters => get!(num_chapters),
+        Named::Comments => get!(num_comments),
+        Named::Dislikes => get!(num_dislikes),
+        Named::Likes => get!(num_likes),
+        Named::TotalViews => get!(total_num_views),
+        Named::Views => get!(num_views),
+        Named::Words => get!(num_words),
+        Named::LikeRatio => Source::Metric(Story::like_ratio),
+        Named::WilsonScore => Source::Metric(wilson_score),
+        Named::ViewsPerWord => Source::Metric(Story::views_per_word),
+        Named::MaxChapterWords => Source::Metric(max_chapter_words),
+        Named::MinChapterWords => Source::Metric(min_chapter_words),
+        Named::AvgChapterWords => Source::Metric(Story::avg_chapter_words),
+        Named::Rating => get!(content_rating),
+        Named::Completion => get!(completion_status),
+        Named::Submitted => get!(submitted),
+        Named::Visible => Source::Bool(&visible),
+        Named::AuthorName => get!(author.name),
+        Named::AuthorId => get!(author.id),
+        Named::AuthorJoined => get!(author.date_joined),
+        Named::AuthorFollowers => get!(author.num_followers),
+        Named::AuthorStories => get!(author.num_stories),
+        Named::AuthorBlogPosts => get!(author.num_blog_posts),
+        Named::Path => get!(archive.path),
+        Named::Tag => Source::Tag(None),
+        Named::Tags => Source::Tags,
+        Named::Character => Source::Tag(Some(TagKind::Character)),
+        Named::Genre => Source::Tag(Some(TagKind::Genre)),
+        Named::Series => Source::Tag(Some(TagKind::Series)),
+        Named::Warning => Source::Tag(Some(TagKind::Warning)),
+    }
+}
+
+fn visible(story: &Story) -> &bool {
+    match story.status {
+        Status::Visible => &true,
+        _ => &false,
+    }
+}
+
+macro_rules! has {
+    ($($tag:literal => $($path:ident).+),+,) => {
+        alt(($(map(tag($tag), |field: &str| {
+            Ast::Has(Has {
+                field: field.into(),
+                check: |story| story.$($path).+.is_some(),
+            })
+        })),+))
+    };
+}
+
+fn exists(input: &str) -> IResult<&str, Ast> {
+    let field = has! {
+        "prequel" => prequel,
+        "cover image" => cover_image,
+        "cover" => cover_image,
+        "color" => color,
+        "modified date" => date_modified,
+        "modified" => date_modified,
+        "published date" => date_published,
+        "published" => date_published,
+        "updated date" => date_updated,
+        "updated" => date_updated,
+        "author avatar" => author.avatar,
+        "author bio" => author.bio_html,
+        "author joined" => author.date_joined,
+    };
+
+    let keyword = alt((tag("has"), tag("exists")));
+    let sequel = value(Ast::Sequel(Sequel::Exists), tag("sequel"));
+
+    preceded(pair(keyword, space1), alt((sequel, field)))(input)
+}
+
+fn members(input: &str) -> IResult<&str, Ast> {
+    let id = preceded(space0, map(map_res(digit1, str::parse), StoryId));
+    let sep = preceded(space0, char(','));
+
+    let list = delimited(
+        char('('),
+        separated_list1(sep, id),
+        preceded(space0, char(')')),
+    );
+
+    let file = delimited(
+        pair(tag("file"), preceded(space0, char('('))),
+        target,
+        char(')'),
+    );
+
+    let members = alt((
+        map(list, Members::List),
+        map(file, |path| Members::File(path.into())),
+    ));
+
+    let keyword = tuple((tag("id"), space1, tag("in"), space0));
+
+    map(preceded(keyword, members), Ast::In)(input)
+}
+
+pub(crate) fn operator(input: &str) -> IResult<&str, Op> {
+    let limit = opt(terminated(number, space1));
+    let approximate = map(preceded(tag("~="), limit), Op::Approximate);
+
     let operator = alt((
+        approximate,
+        value(Op::NotEqual, tag("!=")),
+        value(Op::AtMost, tag("<=")),
+        value(Op::AtLeast, tag(">=")),
         value(Op::Exact, char('=')),
         value(Op::Fuzzy, char(':')),
         value(Op::LessThan, char('<')),
@@ -117,103 +474,383 @@ fn unescape(input: &str) -> String {
         .replace("\\\\", "\\")
 }
 
+fn plain(input: &str) -> IResult<&str, char> {
+    let clauses = preceded(space1, clauses);
+    preceded(not(clauses), none_of("),|\\"))(input)
+}
+
 fn evalue(input: &str) -> IResult<&str, &str> {
-    escaped(none_of("),|\\"), '\\', one_of("),|\\"))(input)
+    escaped(plain, '\\', one_of("),|\\"))(input)
+}
+
+fn unquote(input: &str) -> String {
+    let mut value = String::with_capacity(input.len());
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.extend(chars.next()),
+            c => value.push(c),
+        }
+    }
+
+    value
+}
+
+fn qvalue(input: &str) -> IResult<&str, &str> {
+    escaped(none_of("\"\\"), '\\', one_of("\"\\"))(input)
+}
+
+fn quoted(input: &str) -> IResult<&str, String> {
+    let value = map(opt(qvalue), |value| unquote(value.unwrap_or("")));
+
+    terminated(delimited(char('"'), value, char('"')), space0)(input)
+}
+
+fn unquoted(input: &str) -> IResult<&str, String> {
+    map(evalue, |value| unescape(value.trim()))(input)
 }
 
 fn target(input: &str) -> IResult<&str, String> {
-    preceded(space0, map(evalue, |value| unescape(value.trim())))(input)
+    preceded(space0, alt((quoted, unquoted)))(input)
+}
+
+#[derive(Clone, Copy)]
+enum Modifier {
+    Case(Case),
+    Fold,
 }
 
-fn item(input: &str) -> IResult<&str, Filter> {
-    let result = tuple((source, operator, target))(input)?;
-    let (left, (src, op, value)) = result;
+fn modifiers(input: &str) -> IResult<&str, (Option<Case>, bool)> {
+    let modifier = alt((
+        value(Modifier::Case(Case::Sensitive), tag("cs")),
+        value(Modifier::Case(Case::Insensitive), tag("ci")),
+        value(Modifier::Fold, tag("ai")),
+    ));
+
+    let modifiers = fold_many1(modifier, Default::default, |acc, m| {
+        let (case, fold) = acc;
+
+        match m {
+            Modifier::Case(case) => (Some(case), fold),
+            Modifier::Fold => (case, true),
+        }
+    });
 
-    let Ok(filter) = optimize(src, op, &value) else {
-        let error = NomError::new(input, NomErrorKind::Permutation);
-        return Err(NomErr::Failure(error));
+    let group = delimited(char('['), modifiers, char(']'));
+
+    terminated(group, space0)(input)
+}
+
+fn item(input: &str) -> IResult<&str, Ast> {
+    let parts = (consumed(source), operator, opt(modifiers), target);
+    let (left, ((field, source), op, flags, value)) = tuple(parts)(input)?;
+    let (case, fold) = flags.unwrap_or_default();
+
+    let term = Term {
+        field: field.trim().into(),
+        op,
+        case,
+        fold,
+        value,
+        source,
     };
 
-    Ok((left, filter))
+    Ok((left, Ast::Term(term)))
+}
+
+fn sequel(input: &str) -> IResult<&str, Ast> {
+    let keyword = tuple((tag("sequel"), space1, tag("of"), space1));
+    let id = map(map_res(digit1, str::parse), StoryId);
+
+    map(preceded(keyword, id), |id| Ast::Sequel(Sequel::Of(id)))(input)
 }
 
-fn parens(input: &str) -> IResult<&str, Filter> {
+fn numeric(input: &str) -> IResult<&str, (&str, Source)> {
+    verify(consumed(source), |(_, source)| source.is_numeric())(input)
+}
+
+fn compare(input: &str) -> IResult<&str, Ast> {
+    let stop = alt((eof, tag(","), tag("|"), tag(")")));
+    let end = peek(alt((
+        preceded(space0, stop),
+        recognize(preceded(space1, clauses)),
+    )));
+    let parts = (numeric, operator, numeric, end);
+    let (left, ((lhs, a), op, (rhs, b), _)) = tuple(parts)(input)?;
+
+    let compare = Compare {
+        left: lhs.trim().into(),
+        op,
+        right: rhs.trim().into(),
+        sources: Box::new((a, b)),
+    };
+
+    Ok((left, Ast::Compare(compare)))
+}
+
+fn parens(input: &str) -> IResult<&str, Ast> {
     let group = delimited(
         preceded(space0, char('(')),
         preceded(space0, ofunc),
         preceded(space0, char(')')),
     );
 
-    alt((group, item))(input)
+    alt((group, exists, members, sequel, compare, item))(input)
 }
 
-fn negate(input: &str) -> IResult<&str, Filter> {
-    let (input, filter) = parens(input)?;
-    Ok((input, Box::new(move |s| !filter(s))))
+fn negate(input: &str) -> IResult<&str, Ast> {
+    let (input, ast) = parens(input)?;
+    Ok((input, Ast::Not(Box::new(ast))))
 }
 
-fn nlist(input: &str) -> IResult<&str, Filter> {
+fn nlist(input: &str) -> IResult<&str, Ast> {
     let negated = preceded(char('!'), negate);
     preceded(space0, alt((negated, parens)))(input)
 }
 
-fn alist(input: &str) -> IResult<&str, Vec<Filter>> {
+fn alist(input: &str) -> IResult<&str, Vec<Ast>> {
     let sep = preceded(space0, char(','));
     separated_list1(sep, nlist)(input)
 }
 
-fn afunc(input: &str) -> IResult<&str, Filter> {
-    let (left, mut filters) = alist(input)?;
+fn afunc(input: &str) -> IResult<&str, Ast> {
+    let (left, mut asts) = alist(input)?;
 
-    if filters.len() == 1 {
-        return Ok((left, filters.remove(0)));
+    if asts.len() == 1 {
+        return Ok((left, asts.remove(0)));
     }
 
-    let filter: Filter = Box::new(move |story| {
-        for filter in filters.iter() {
-            if !filter(story) {
-                return false;
-            }
-        }
-
-        true
-    });
-
-    Ok((left, filter))
+    Ok((left, Ast::And(asts)))
 }
 
-fn olist(input: &str) -> IResult<&str, Vec<Filter>> {
+fn olist(input: &str) -> IResult<&str, Vec<Ast>> {
     let sep = preceded(space0, char('|'));
     separated_list1(sep, afunc)(input)
 }
 
-fn ofunc(input: &str) -> IResult<&str, Filter> {
-    let (left, mut filters) = olist(input)?;
+fn ofunc(input: &str) -> IResult<&str, Ast> {
+    let (left, mut asts) = olist(input)?;
 
-    if filters.len() == 1 {
-        return Ok((left, filters.remove(0)));
+    if asts.len() == 1 {
+        return Ok((left, asts.remove(0)));
     }
 
-    let filter: Filter = Box::new(move |story| {
-        for filter in filters.iter() {
-            if filter(story) {
-                return true;
+    Ok((left, Ast::Or(asts)))
+}
+
+#[derive(Clone, Copy)]
+enum Clause {
+    Sort(SortKey, Order),
+    Limit(usize),
+    Offset(usize),
+    Random(usize, Option<u64>),
+}
+
+fn number(input: &str) -> IResult<&str, usize> {
+    map_res(digit1, str::parse)(input)
+}
+
+fn sorting(input: &str) -> IResult<&str, Clause> {
+    let key = alt((
+        value(SortKey::Title, tag("title")),
+        value(SortKey::Words, tag("words")),
+        value(SortKey::Likes, tag("likes")),
+        value(SortKey::Published, tag("published")),
+    ));
+
+    let order = alt((
+        value(Order::Ascending, tag("asc")),
+        value(Order::Descending, tag("desc")),
+    ));
+
+    let clause = tuple((
+        tag("sort"),
+        space1,
+        tag("by"),
+        space1,
+        key,
+        opt(preceded(space1, order)),
+    ));
+
+    map(clause, |(_, _, _, _, key, order)| {
+        Clause::Sort(key, order.unwrap_or_default())
+    })(input)
+}
+
+fn random(input: &str) -> IResult<&str, Clause> {
+    let seed = preceded(tuple((space1, tag("seed"), space1)), digit1);
+    let seed = map_res(seed, str::parse);
+
+    let clause =
+        pair(preceded(pair(tag("random"), space1), number), opt(seed));
+
+    map(clause, |(count, seed)| Clause::Random(count, seed))(input)
+}
+
+fn clause(input: &str) -> IResult<&str, Clause> {
+    let limit =
+        map(preceded(pair(tag("limit"), space1), number), Clause::Limit);
+    let offset = map(
+        preceded(pair(tag("offset"), space1), number),
+        Clause::Offset,
+    );
+
+    alt((sorting, limit, offset, random))(input)
+}
+
+fn clauses(input: &str) -> IResult<&str, Vec<Clause>> {
+    let list = preceded(space0, separated_list1(space1, clause));
+    terminated(list, pair(space0, eof))(input)
+}
+
+fn builder(clauses: Vec<Clause>) -> Result<QueryBuilder> {
+    let mut query = QueryBuilder::default();
+
+    for clause in clauses {
+        let duplicate = match clause {
+            Clause::Sort(key, order) => {
+                query.sort.replace((key, order)).is_some()
             }
+            Clause::Limit(limit) => query.limit.replace(limit).is_some(),
+            Clause::Offset(offset) => replace(&mut query.offset, offset) != 0,
+            Clause::Random(count, seed) => {
+                query.random.replace((count, seed)).is_some()
+            }
+        };
+
+        if duplicate {
+            return Err(Error::query("Duplicate query clause"));
         }
+    }
 
-        false
-    });
+    Ok(query)
+}
 
-    Ok((left, filter))
+fn complete(input: &str) -> IResult<&str, (Ast, Vec<Clause>)> {
+    let clauses = map(opt(clauses), Option::unwrap_or_default);
+    terminated(pair(ofunc, clauses), eof)(input)
 }
 
-fn complete(input: &str) -> IResult<&str, Filter> {
-    terminated(ofunc, eof)(input.trim())
+pub fn parse(query: &str) -> Result<Query> {
+    parse_with(query, &Limits::default())
 }
 
-pub fn parse(query: &str) -> Result<Filter> {
+pub fn parse_with(query: &str, limits: &Limits) -> Result<Query> {
+    let query = query.trim();
+    limits.check_length(query)?;
+    limits.check_depth(query)?;
+
+    if let Ok((_, clauses)) = clauses(query) {
+        return builder(clauses)?.limits(*limits).build();
+    }
+
     match complete(query).finish() {
-        Ok((_, filter)) => Ok(filter),
-        Err(e) => Err(Error::query(e)),
+        Ok((_, (ast, clauses))) => {
+            let parsed = builder(clauses)?.limits(*limits);
+            QueryBuilder { ast, ..parsed }.build()
+        }
+        Err(e) => Err(diagnose(query, query.len() - e.input.len()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fimfareader::archive::Order;
+    use fimfareader::archive::SortKey;
+
+    use super::parse;
+    use crate::ast::Ast;
+    use crate::ast::Case;
+
+    fn value(query: &str) -> String {
+        match parse(query).unwrap().ast() {
+            Ast::Term(term) => term.value.clone(),
+            ast => panic!("Unexpected {ast:?}"),
+        }
+    }
+
+    #[test]
+    fn keeps_clause_words_in_values() {
+        assert_eq!(value("title:random 5"), "random 5");
+        assert_eq!(value("description:limit 10"), "limit 10");
+        assert_eq!(value("title: offset 3"), "offset 3");
+        assert_eq!(value("title: sort by words"), "sort by words");
+        assert_eq!(value("title: random walk"), "random walk");
+    }
+
+    #[test]
+    fn parses_clauses_after_predicate() {
+        let query = parse("title: random walk limit 5 offset 2").unwrap();
+
+        assert_eq!(query.limit, Some(5));
+        assert_eq!(query.offset, 2);
+        assert_eq!(value("title: random walk limit 5"), "random walk");
+    }
+
+    #[test]
+    fn parses_clauses_after_compare() {
+        let query = parse("likes > dislikes sort by words desc").unwrap();
+
+        assert_eq!(query.sort, Some((SortKey::Words, Order::Descending)));
+    }
+
+    #[test]
+    fn parses_bracketed_modifiers() {
+        let term = |query: &str| match parse(query).unwrap().ast() {
+            Ast::Term(term) => term.clone(),
+            ast => panic!("Unexpected {ast:?}"),
+        };
+
+        let plain = term("title:cs lewis");
+        assert_eq!(plain.value, "cs lewis");
+        assert_eq!(plain.case, None);
+
+        let plain = term("title:ai");
+        assert_eq!(plain.value, "ai");
+        assert!(!plain.fold);
+
+        let flagged = term("title:[csai] lewis");
+        assert_eq!(flagged.value, "lewis");
+        assert_eq!(flagged.case, Some(Case::Sensitive));
+        assert!(flagged.fold);
+
+        let shown = flagged.to_string();
+        assert_eq!(shown, "title :[csai] \"lewis\"");
+        assert_eq!(term(&shown).to_string(), shown);
+    }
+
+    #[test]
+    fn looks_up_folded_tags() {
+        assert!(parse("tag = Adventure").unwrap().lookup().is_some());
+        assert!(parse("tag =[ai] Adventure").unwrap().lookup().is_some());
+        assert!(parse("tags =[ai] Adventure").unwrap().lookup().is_some());
+    }
+
+    #[test]
+    fn parses_clauses_alone() {
+        let query = parse("random 3 seed 7").unwrap();
+
+        assert_eq!(query.random, Some((3, Some(7))));
+        assert!(parse("limit 1 limit 2").is_err());
+    }
+
+    #[test]
+    fn orders_cheaper_terms_first() {
+        let query = parse("description ~= x, title: y, words > 5").unwrap();
+
+        let Ast::And(asts) = query.ast() else {
+            panic!("Unexpected {:?}", query.ast());
+        };
+
+        let fields: Vec<_> = asts
+            .iter()
+            .map(|ast| match ast {
+                Ast::Term(term) => term.field.as_str(),
+                ast => panic!("Unexpected {ast:?}"),
+            })
+            .collect();
+
+        assert_eq!(fields, ["words", "title", "description"]);
     }
 }
@@ -0,0 +1,189 @@
+//! Query type.
+
+use std::io::Read;
+use std::io::Seek;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use fimfareader::archive::Fetcher;
+use fimfareader::archive::Lookup;
+use fimfareader::archive::Order;
+use fimfareader::archive::QueryPlan;
+use fimfareader::archive::SortKey;
+use fimfareader::archive::Story;
+use fimfareader::error::ErrorBuilder;
+use fimfareader::error::ErrorKind;
+use fimfareader::error::Result;
+
+use crate::ast::Ast;
+use crate::filter::Filter;
+
+const BUDGET_CHUNK: usize = 1024;
+
+pub struct Query {
+    pub(crate) ast: Ast,
+    pub(crate) filter: Filter,
+    pub(crate) sort: Option<(SortKey, Order)>,
+    pub(crate) offset: usize,
+    pub(crate) limit: Option<usize>,
+    pub(crate) random: Option<(usize, Option<u64>)>,
+    pub(crate) budget: Option<Duration>,
+}
+
+impl Query {
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    pub fn lookup(&self) -> Option<Lookup> {
+        self.ast.lookup()
+    }
+
+    pub fn plan(&self) -> QueryPlan {
+        QueryPlan {
+            lookup: self.lookup(),
+            sort: self.sort,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+
+    pub fn explain(&self) -> String {
+        let mut out = String::new();
+
+        if let Some(lookup) = self.lookup() {
+            out.push_str(&format!("index {lookup}\n"));
+        }
+
+        self.ast.explain(0, &mut out);
+
+        if let Some((count, seed)) = self.random {
+            match seed {
+                Some(seed) => {
+                    out.push_str(&format!("random {count} seed {seed}\n"))
+                }
+                None => out.push_str(&format!("random {count}\n")),
+            }
+        }
+
+        if let Some((key, order)) = self.sort {
+            let key = format!("{key:?}").to_lowercase();
+            let order = format!("{order:?}").to_lowercase();
+
+            out.push_str(&format!("sort by {key} {order}\n"));
+        }
+
+        if self.offset != 0 {
+            out.push_str(&format!("offset {}\n", self.offset));
+        }
+
+        if let Some(limit) = self.limit {
+            out.push_str(&format!("limit {limit}\n"));
+        }
+
+        out
+    }
+
+    pub fn matches(&self, story: &Story) -> bool {
+        self.filter.matches(story)
+    }
+
+    pub fn sort(&self) -> Option<(SortKey, Order)> {
+        self.sort
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    pub fn random(&self) -> Option<(usize, Option<u64>)> {
+        self.random
+    }
+
+    pub fn budget(&self) -> Option<Duration> {
+        self.budget
+    }
+
+    pub fn apply<'a, T>(&self, fetcher: &'a Fetcher<T>) -> Vec<&'a Story>
+    where
+        T: Read + Seek,
+    {
+        self.run(fetcher, &self.filter.predicate)
+    }
+
+    pub fn try_apply<'a, T>(
+        &self,
+        fetcher: &'a Fetcher<T>,
+    ) -> Result<Vec<&'a Story>>
+    where
+        T: Read + Seek,
+    {
+        let Some(budget) = self.budget else {
+            return Ok(self.apply(fetcher));
+        };
+
+        let deadline = Instant::now() + budget;
+        let calls = AtomicUsize::new(0);
+        let expired = AtomicBool::new(false);
+
+        let filter = |s: &Story| {
+            let call = calls.fetch_add(1, Ordering::Relaxed);
+
+            if call.is_multiple_of(BUDGET_CHUNK) && Instant::now() >= deadline
+            {
+                expired.store(true, Ordering::Relaxed);
+            }
+
+            !expired.load(Ordering::Relaxed) && self.matches(s)
+        };
+
+        let stories = self.run(fetcher, &filter);
+
+        match expired.into_inner() {
+            false => Ok(stories),
+            true => Err(ErrorBuilder::new(ErrorKind::Cancelled)
+                .message("Query exceeded its time budget")
+                .build()),
+        }
+    }
+
+    fn run<'a, T, F>(
+        &self,
+        fetcher: &'a Fetcher<T>,
+        filter: &F,
+    ) -> Vec<&'a Story>
+    where
+        T: Read + Seek,
+        F: Sync + Fn(&Story) -> bool,
+    {
+        self.filter.bind(fetcher);
+
+        let Some((count, seed)) = self.random else {
+            return fetcher.query(filter, &self.plan());
+        };
+
+        let mut stories = match seed {
+            Some(seed) => fetcher.sample_seeded(filter, count, seed),
+            None => fetcher.sample_where(filter, count),
+        };
+
+        if let Some((key, order)) = self.sort {
+            key.sort(&mut stories, order);
+        }
+
+        let limit = self.limit.unwrap_or(usize::MAX);
+
+        stories.into_iter().skip(self.offset).take(limit).collect()
+    }
+}
@@ -0,0 +1,102 @@
+//! Main module.
+
+mod render;
+
+use std::collections::HashMap;
+use std::env::args;
+use std::error::Error;
+use std::fs::create_dir_all;
+use std::fs::read_to_string;
+use std::fs::write;
+use std::io::Read;
+use std::io::Seek;
+use std::path::Path;
+use std::result::Result;
+
+use fimfareader::archive::Cover;
+use fimfareader::archive::Fetcher;
+use fimfareader::archive::Story;
+use fimfareader::archive::StoryId;
+use fimfareader_query::parse;
+
+fn select<'a, T>(
+    fetcher: &'a Fetcher<T>,
+    arg: &str,
+) -> Result<Vec<&'a Story>, Box<dyn Error>>
+where
+    T: Read + Seek,
+{
+    let Some(path) = arg.strip_prefix('@') else {
+        return Ok(parse(arg)?.apply(fetcher));
+    };
+
+    let mut stories = Vec::new();
+
+    for line in read_to_string(path)?.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match fetcher.fetch(line.parse::<StoryId>()?) {
+            Some(story) => stories.push(story),
+            None => eprintln!("Skipping unknown story {line}."),
+        }
+    }
+
+    Ok(stories)
+}
+
+fn extension(media_type: &str) -> Option<&'static str> {
+    match media_type {
+        "image/jpeg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let argv = args().collect::<Vec<String>>();
+
+    if argv.len() != 4 {
+        eprintln!("Usage: fimfareader-site <ARCHIVE> <QUERY|@SHELF> <OUTPUT>");
+        std::process::exit(1);
+    }
+
+    let fetcher = Fetcher::new(&argv[1])?;
+    let stories = select(&fetcher, &argv[2])?;
+    let output = Path::new(&argv[3]);
+
+    create_dir_all(output.join("cover"))?;
+    create_dir_all(output.join("epub"))?;
+    create_dir_all(output.join("story"))?;
+
+    let mut covers = HashMap::new();
+
+    for story in stories.iter() {
+        let epub = output.join(format!("epub/{}.epub", story.id));
+        let page = output.join(format!("story/{}.html", story.id));
+
+        write(epub, fetcher.read(story)?)?;
+
+        if let Some(Cover::Embedded(image)) = fetcher.cover(story)? {
+            if let Some(extension) = extension(&image.media_type) {
+                let path = format!("cover/{}.{}", story.id, extension);
+
+                write(output.join(&path), image.data)?;
+                covers.insert(story.id, path);
+            }
+        }
+
+        write(page, render::story(story, covers.get(&story.id)))?;
+    }
+
+    write(output.join("index.html"), render::index(&stories, &covers))?;
+
+    println!("Wrote {} stories to {}.", stories.len(), output.display());
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! Page renderer.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use fimfareader::archive::Story;
+use fimfareader::archive::StoryId;
+use fimfareader::epub::sanitize;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; }
+li { display: flex; gap: 1em; margin-bottom: 1em; list-style: none; }
+img { height: 8em; object-fit: cover; width: 6em; }
+.tags span { background: #ddd; border-radius: 0.3em; padding: 0 0.3em; }
+";
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn link(story: &Story) -> String {
+    let url = story.url.to_ascii_lowercase();
+
+    match url.starts_with("https://") || url.starts_with("http://") {
+        true => escape(&story.url),
+        false => format!("https://www.fimfiction.net/story/{}", story.id),
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n\
+         <body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body,
+    )
+}
+
+fn meta(story: &Story) -> String {
+    let date = match story.date_published {
+        Some(date) => date.format("%Y-%m-%d").to_string(),
+        None => String::from("unpublished"),
+    };
+
+    format!(
+        "by {}, {} words, {}, {}, {}",
+        escape(&story.author.name),
+        story.num_words,
+        story.content_rating,
+        story.completion_status,
+        date,
+    )
+}
+
+fn tags(story: &Story) -> String {
+    let mut html = String::from("<p class=\"tags\">");
+
+    for tag in story.tags.iter() {
+        write!(html, "<span>{}</span> ", escape(&tag.name)).unwrap();
+    }
+
+    html.push_str("</p>\n");
+    html
+}
+
+fn cover(path: Option<&String>, prefix: &str) -> String {
+    match path {
+        Some(path) => {
+            format!("<img src=\"{}{}\" alt=\"\">", prefix, escape(path))
+        }
+        None => String::from("<img alt=\"\">"),
+    }
+}
+
+pub fn index(stories: &[&Story], covers: &HashMap<StoryId, String>) -> String {
+    let mut body = format!("<h1>{} stories</h1>\n<ul>\n", stories.len());
+
+    for story in stories.iter() {
+        write!(
+            body,
+            "<li>{}<div>\n<a href=\"story/{}.html\"><b>{}</b></a>\n\
+             <p>{}</p>\n<p>{}</p>\n{}</div></li>\n",
+            cover(covers.get(&story.id), ""),
+            story.id,
+            escape(&story.title),
+            meta(story),
+            escape(&story.short_description),
+            tags(story),
+        )
+        .unwrap();
+    }
+
+    body.push_str("</ul>\n");
+    page("Reading list", &body)
+}
+
+pub fn story(story: &Story, cover_path: Option<&String>) -> String {
+    let mut body = String::new();
+
+    write!(
+        body,
+        "<p><a href=\"../index.html\">Back to list</a></p>\n\
+         <h1>{}</h1>\n{}\n<p>{}</p>\n{}{}\n\
+         <p><a href=\"../epub/{}.epub\">Download EPUB</a> \
+         | <a href=\"{}\">Fimfiction</a></p>\n<ol>\n",
+        escape(&story.title),
+        cover(cover_path, "../"),
+        meta(story),
+        tags(story),
+        sanitize(&story.description_html),
+        story.id,
+        link(story),
+    )
+    .unwrap();
+
+    for chapter in story.chapters.iter() {
+        writeln!(
+            body,
+            "<li>{} ({} words)</li>",
+            escape(&chapter.title),
+            chapter.num_words,
+        )
+        .unwrap();
+    }
+
+    body.push_str("</ol>\n");
+    page(&story.title, &body)
+}
@@ -0,0 +1,234 @@
+//! Fetcher builder.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::io::ErrorKind as IoErrorKind;
+use std::io::Read;
+use std::io::Seek;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rayon::ThreadPool;
+use rayon::ThreadPoolBuilder;
+
+use super::cancel::CancelToken;
+use super::fetcher::Fetcher;
+use super::handle::Handle;
+use super::parser::Duplicates;
+use super::remote::Remote;
+use super::schema::Schema;
+use super::storage::Storage;
+use super::story::Story;
+use crate::error::Error;
+use crate::error::Result;
+
+pub(crate) type Predicate = Arc<dyn Fn(&Story) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+pub(crate) struct Options {
+    pub buffer: usize,
+    pub lazy: bool,
+    pub interned: bool,
+    pub strict: bool,
+    pub skip_invalid: bool,
+    pub duplicates: Duplicates,
+    pub pool: Option<Arc<ThreadPool>>,
+    pub cache: Option<PathBuf>,
+    pub predicate: Option<Predicate>,
+    pub cancel: Option<CancelToken>,
+    pub seed: Option<u64>,
+    pub schema: Option<Schema>,
+}
+
+pub struct FetcherBuilder {
+    options: Options,
+    threads: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            buffer: 1048576,
+            lazy: false,
+            interned: false,
+            strict: true,
+            skip_invalid: false,
+            duplicates: Duplicates::Error,
+            pool: None,
+            cache: None,
+            predicate: None,
+            cancel: None,
+            seed: None,
+            schema: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+}
+
+impl Default for FetcherBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FetcherBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: Options::default(),
+            threads: None,
+        }
+    }
+
+    pub fn buffer_size(mut self, bytes: usize) -> Self {
+        self.options.buffer = bytes.max(1);
+        self
+    }
+
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.options.lazy = lazy;
+        self
+    }
+
+    pub fn keep_interned(mut self, keep: bool) -> Self {
+        self.options.interned = keep;
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    pub fn skip_invalid(mut self, skip: bool) -> Self {
+        self.options.skip_invalid = skip;
+        self
+    }
+
+    pub fn duplicates(mut self, policy: Duplicates) -> Self {
+        self.options.duplicates = policy;
+        self
+    }
+
+    pub fn threads(mut self, count: usize) -> Self {
+        self.threads = Some(count);
+        self
+    }
+
+    pub fn cache(mut self, directory: impl AsRef<Path>) -> Self {
+        self.options.cache = Some(directory.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn predicate<F>(mut self, function: F) -> Self
+    where
+        F: Fn(&Story) -> bool + Send + Sync + 'static,
+    {
+        self.options.predicate = Some(Arc::new(function));
+        self
+    }
+
+    pub fn cancel(mut self, token: CancelToken) -> Self {
+        self.options.cancel = Some(token);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.options.schema = Some(schema);
+        self
+    }
+
+    fn build<T>(mut self, storage: Storage<T>) -> Result<Fetcher<T>>
+    where
+        T: Read + Seek,
+    {
+        if let Some(count) = self.threads {
+            let builder = ThreadPoolBuilder::new().num_threads(count);
+
+            let Ok(pool) = builder.build() else {
+                return Err(Error::usage("Could not create thread pool"));
+            };
+
+            self.options.pool = Some(Arc::new(pool));
+        }
+
+        Fetcher::with_storage(storage, self.options)
+    }
+
+    pub fn open(
+        self,
+        path: impl AsRef<Path>,
+    ) -> Result<Fetcher<BufReader<File>>> {
+        use IoErrorKind::*;
+
+        let path = path.as_ref().to_path_buf();
+        let display = path.display().to_string();
+
+        if path.is_dir() {
+            let fetcher = self.build(Storage::directory(path));
+            return fetcher.map_err(|e| e.with_path(display));
+        }
+
+        let opener = move || File::open(&path).map(BufReader::new);
+
+        let file = opener().map_err(|e| match e.kind() {
+            NotFound => Error::archive("File not found"),
+            _ => Error::archive("Could not open file"),
+        });
+
+        let file = file.map_err(|e| e.with_path(&display))?;
+
+        let Ok(handle) = Handle::with_opener(file, opener) else {
+            let error = Error::archive("Could not read archive");
+            return Err(error.with_path(display));
+        };
+
+        let storage =
+            Storage::zip(handle).map_err(|e| e.with_path(&display))?;
+
+        self.build(storage).map_err(|e| e.with_path(display))
+    }
+
+    pub fn reader<T: Read + Seek>(self, reader: T) -> Result<Fetcher<T>> {
+        let Ok(handle) = Handle::new(reader) else {
+            return Err(Error::archive("Could not read archive"));
+        };
+
+        self.build(Storage::zip(handle)?)
+    }
+
+    /// Only plain `http://` URLs are supported, there is no TLS.
+    pub fn remote(self, url: &str) -> Result<Fetcher<Remote>> {
+        let Ok(remote) = Remote::http(url) else {
+            return Err(Error::archive("Could not reach remote archive"));
+        };
+
+        let shared = remote.clone();
+        let opener = move || Ok(shared.clone());
+
+        remote.pin_reads(true);
+
+        let Ok(handle) = Handle::with_opener(remote.clone(), opener) else {
+            return Err(Error::archive("Could not read archive"));
+        };
+
+        let storage = Storage::zip(handle)?;
+        remote.pin_reads(false);
+
+        if let Some((start, end)) = storage.index_range() {
+            remote.pin(start, end);
+        }
+
+        self.build(storage)
+    }
+}
@@ -0,0 +1,519 @@
+//! Index cache.
+
+use std::collections::HashMap;
+use std::fs::rename;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Write;
+use std::mem::size_of;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::prelude::*;
+
+use super::identity::ArchiveIdentity;
+use super::story::*;
+#[cfg(feature = "compact")]
+use super::text::text;
+
+const MAGIC: &[u8; 8] = b"FIMFACHE";
+const VERSION: u32 = 3;
+
+struct Table<'a, T> {
+    ids: HashMap<&'a T, u32>,
+    items: Vec<&'a T>,
+}
+
+struct Encoder<'a, W> {
+    writer: W,
+    authors: Table<'a, Author>,
+    tags: Table<'a, Tag>,
+}
+
+struct Decoder<R> {
+    reader: R,
+    authors: Vec<Arc<Author>>,
+    tags: Vec<Arc<Tag>>,
+}
+
+trait Codec: Sized {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()>;
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self>;
+}
+
+fn invalid(message: &str) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, message)
+}
+
+impl<'a, T: Eq + Hash> Table<'a, T> {
+    fn new() -> Self {
+        Self {
+            ids: HashMap::new(),
+            items: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, item: &'a T) {
+        let next = self.items.len() as u32;
+
+        self.ids.entry(item).or_insert_with(|| {
+            self.items.push(item);
+            next
+        });
+    }
+
+    fn id(&self, item: &T) -> IoResult<u32> {
+        match self.ids.get(item) {
+            Some(id) => Ok(*id),
+            None => Err(invalid("Missing table entry")),
+        }
+    }
+}
+
+macro_rules! number {
+    ($($type:ty),+) => {$(
+        impl Codec for $type {
+            fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+                e.writer.write_all(&self.to_le_bytes())
+            }
+
+            fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+                let mut buf = [0; size_of::<$type>()];
+                d.reader.read_exact(&mut buf)?;
+
+                Ok(<$type>::from_le_bytes(buf))
+            }
+        }
+    )+};
+}
+
+macro_rules! record {
+    ($($type:ident { $($field:ident),+ $(,)? })+) => {$(
+        impl Codec for $type {
+            fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+                $(self.$field.encode(e)?;)+
+                Ok(())
+            }
+
+            fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+                Ok(Self {
+                    $($field: Codec::decode(d)?,)+
+                })
+            }
+        }
+    )+};
+}
+
+macro_rules! variants {
+    ($($type:ident { $($variant:ident),+ $(,)? })+) => {$(
+        impl Codec for $type {
+            fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+                let variants = [$($type::$variant),+];
+
+                match variants.iter().position(|v| v == self) {
+                    Some(tag) => (tag as u8).encode(e),
+                    None => Err(invalid("Unknown variant")),
+                }
+            }
+
+            fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+                let variants = [$($type::$variant),+];
+                let tag = u8::decode(d)? as usize;
+
+                match variants.into_iter().nth(tag) {
+                    Some(variant) => Ok(variant),
+                    None => Err(invalid("Unknown variant")),
+                }
+            }
+        }
+    )+};
+}
+
+number!(u8, u32, u64, i32, i64);
+
+impl Codec for StoryId {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        self.0.encode(e)
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        i32::decode(d).map(StoryId)
+    }
+}
+
+impl Codec for TagKind {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        Box::<str>::from(self.as_str()).encode(e)
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        Ok(TagKind::from(&*Box::<str>::decode(d)?))
+    }
+}
+
+impl Codec for bool {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        (*self as u8).encode(e)
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        Ok(u8::decode(d)? != 0)
+    }
+}
+
+impl Codec for Box<str> {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        (self.len() as u64).encode(e)?;
+        e.writer.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        let size = u64::decode(d)?;
+        let mut buf = Vec::new();
+
+        (&mut d.reader).take(size).read_to_end(&mut buf)?;
+
+        if buf.len() as u64 != size {
+            return Err(IoErrorKind::UnexpectedEof.into());
+        }
+
+        match String::from_utf8(buf) {
+            Ok(text) => Ok(text.into_boxed_str()),
+            Err(_) => Err(invalid("Invalid text encoding")),
+        }
+    }
+}
+
+#[cfg(feature = "compact")]
+impl Codec for Arc<str> {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        Box::<str>::from(&**self).encode(e)
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        Ok(text(Box::<str>::decode(d)?))
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        match self {
+            Some(value) => {
+                true.encode(e)?;
+                value.encode(e)
+            }
+            None => false.encode(e),
+        }
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        match bool::decode(d)? {
+            true => Ok(Some(T::decode(d)?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<T: Codec> Codec for Box<[T]> {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        (self.len() as u64).encode(e)?;
+
+        for item in self.iter() {
+            item.encode(e)?;
+        }
+
+        Ok(())
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        let size = u64::decode(d)?;
+        let mut items = Vec::with_capacity(size.min(1024) as usize);
+
+        for _ in 0..size {
+            items.push(T::decode(d)?);
+        }
+
+        Ok(items.into_boxed_slice())
+    }
+}
+
+impl Codec for DateTime<Utc> {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        self.timestamp().encode(e)?;
+        self.timestamp_subsec_nanos().encode(e)
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        let secs = i64::decode(d)?;
+        let nanos = u32::decode(d)?;
+
+        match DateTime::from_timestamp(secs, nanos) {
+            Some(date) => Ok(date),
+            None => Err(invalid("Invalid timestamp")),
+        }
+    }
+}
+
+impl Codec for Arc<Author> {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        e.authors.id(self)?.encode(e)
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        let id = u32::decode(d)? as usize;
+
+        match d.authors.get(id) {
+            Some(author) => Ok(author.clone()),
+            None => Err(invalid("Unknown author")),
+        }
+    }
+}
+
+impl Codec for Arc<Tag> {
+    fn encode<W: Write>(&self, e: &mut Encoder<'_, W>) -> IoResult<()> {
+        e.tags.id(self)?.encode(e)
+    }
+
+    fn decode<R: Read>(d: &mut Decoder<R>) -> IoResult<Self> {
+        let id = u32::decode(d)? as usize;
+
+        match d.tags.get(id) {
+            Some(tag) => Ok(tag.clone()),
+            None => Err(invalid("Unknown tag")),
+        }
+    }
+}
+
+record! {
+    Story {
+        archive, author, chapters, color, completion_status,
+        content_rating, cover_image, date_modified, date_published,
+        date_updated, description_html, id, num_chapters, num_comments,
+        num_dislikes, num_likes, num_views, num_words, prequel, published,
+        rating, short_description, status, submitted, tags, title,
+        total_num_views, url,
+    }
+
+    Archive {
+        date_checked, date_created, date_fetched, date_updated, path,
+    }
+
+    Author {
+        avatar, bio_html, date_joined, id, name, num_blog_posts,
+        num_followers, num_stories, url,
+    }
+
+    Avatar {
+        x16, x32, x48, x64, x96, x128, x160, x192, x256, x320, x384, x512,
+    }
+
+    Chapter {
+        chapter_number, date_modified, date_published, id, num_views,
+        num_words, published, title, url,
+    }
+
+    Color {
+        red, green, blue,
+    }
+
+    CoverImage {
+        full, large, medium, thumbnail,
+    }
+
+    Tag {
+        id, name, old_id, r#type, url,
+    }
+}
+
+variants! {
+    CompletionStatus {
+        Cancelled, Complete, Hiatus, Incomplete,
+    }
+
+    ContentRating {
+        Everyone, Mature, Teen,
+    }
+
+    Status {
+        ApproveQueue, NotVisible, PostQueue, Visible,
+    }
+}
+
+pub type Diagnostics = Vec<(usize, Box<str>)>;
+
+fn header<W: Write>(
+    e: &mut Encoder<'_, W>,
+    key: &ArchiveIdentity,
+) -> IoResult<()> {
+    e.writer.write_all(MAGIC)?;
+    VERSION.encode(e)?;
+    e.writer.write_all(key.as_bytes())
+}
+
+fn check<R: Read>(d: &mut Decoder<R>, key: &ArchiveIdentity) -> IoResult<()> {
+    let mut magic = [0; MAGIC.len()];
+    d.reader.read_exact(&mut magic)?;
+
+    if &magic != MAGIC || u32::decode(d)? != VERSION {
+        return Err(invalid("Unsupported cache format"));
+    }
+
+    let mut stored = [0; 32];
+    d.reader.read_exact(&mut stored)?;
+
+    if &stored != key.as_bytes() {
+        return Err(invalid("Stale cache"));
+    }
+
+    Ok(())
+}
+
+pub fn save(
+    path: &Path,
+    key: &ArchiveIdentity,
+    stories: &[Story],
+    diagnostics: &[(usize, Box<str>)],
+) -> IoResult<()> {
+    let temp = path.with_extension("tmp");
+
+    let mut e = Encoder {
+        writer: BufWriter::new(File::create(&temp)?),
+        authors: Table::new(),
+        tags: Table::new(),
+    };
+
+    for story in stories {
+        e.authors.insert(&story.author);
+
+        for tag in story.tags.iter() {
+            e.tags.insert(tag);
+        }
+    }
+
+    header(&mut e, key)?;
+
+    let authors = e.authors.items.clone();
+    (authors.len() as u64).encode(&mut e)?;
+
+    for author in authors {
+        author.encode(&mut e)?;
+    }
+
+    let tags = e.tags.items.clone();
+    (tags.len() as u64).encode(&mut e)?;
+
+    for tag in tags {
+        tag.encode(&mut e)?;
+    }
+
+    (stories.len() as u64).encode(&mut e)?;
+
+    for story in stories {
+        story.encode(&mut e)?;
+    }
+
+    (diagnostics.len() as u64).encode(&mut e)?;
+
+    for (line, message) in diagnostics {
+        (*line as u64).encode(&mut e)?;
+        message.encode(&mut e)?;
+    }
+
+    e.writer.flush()?;
+    drop(e);
+
+    rename(temp, path)
+}
+
+pub fn load(
+    path: &Path,
+    key: &ArchiveIdentity,
+) -> IoResult<(Vec<Story>, Diagnostics)> {
+    let mut d = Decoder {
+        reader: BufReader::new(File::open(path)?),
+        authors: Vec::new(),
+        tags: Vec::new(),
+    };
+
+    check(&mut d, key)?;
+
+    let authors = Box::<[Author]>::decode(&mut d)?;
+    d.authors = authors.into_vec().into_iter().map(Arc::new).collect();
+
+    let tags = Box::<[Tag]>::decode(&mut d)?;
+    d.tags = tags.into_vec().into_iter().map(Arc::new).collect();
+
+    let stories = Box::<[Story]>::decode(&mut d)?;
+    let count = u64::decode(&mut d)?;
+    let mut diagnostics = Vec::with_capacity(count.min(1024) as usize);
+
+    for _ in 0..count {
+        let line = u64::decode(&mut d)? as usize;
+        diagnostics.push((line, Box::<str>::decode(&mut d)?));
+    }
+
+    Ok((stories.into_vec(), diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::fs::write;
+    use std::path::PathBuf;
+
+    use super::load;
+    use super::save;
+    use crate::archive::identity::ArchiveIdentity;
+    use crate::archive::story::tests::story;
+
+    fn file(name: &str) -> PathBuf {
+        let pid = std::process::id();
+        temp_dir().join(format!("fimfareader-{name}-{pid}.cache"))
+    }
+
+    #[test]
+    fn round_trips_stories_and_diagnostics() {
+        let path = file("round");
+        let key = ArchiveIdentity::from_bytes([1; 32]);
+        let stories = vec![story(1), story(2)];
+        let diagnostics = vec![(3, Box::from("broken"))];
+
+        save(&path, &key, &stories, &diagnostics).unwrap();
+        let loaded = load(&path, &key).unwrap();
+        remove_file(&path).unwrap();
+
+        assert_eq!(loaded, (stories, diagnostics));
+    }
+
+    #[test]
+    fn rejects_other_keys() {
+        let path = file("stale");
+        let key = ArchiveIdentity::from_bytes([1; 32]);
+        let other = ArchiveIdentity::from_bytes([2; 32]);
+
+        save(&path, &key, &[story(1)], &[]).unwrap();
+        let loaded = load(&path, &other);
+        remove_file(&path).unwrap();
+
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn rejects_foreign_files() {
+        let path = file("foreign");
+        let key = ArchiveIdentity::from_bytes([1; 32]);
+
+        write(&path, b"not a cache at all, just some bytes").unwrap();
+        let loaded = load(&path, &key);
+        remove_file(&path).unwrap();
+
+        assert!(loaded.is_err());
+    }
+}
@@ -0,0 +1,9 @@
+//! Story covers.
+
+use crate::epub::Image;
+
+#[derive(Clone, Debug)]
+pub enum Cover {
+    Embedded(Image),
+    Url(Box<str>),
+}
@@ -0,0 +1,107 @@
+//! Archive diff.
+
+use std::cmp::Ordering;
+use std::io::Read;
+use std::io::Seek;
+
+use super::fetcher::Fetcher;
+use super::story::Story;
+
+macro_rules! changes {
+    ($old:ident, $new:ident, $($name:literal => $($path:ident).+),+,) => {{
+        let mut fields = Vec::new();
+
+        $(if $old.$($path).+ != $new.$($path).+ {
+            fields.push($name);
+        })+
+
+        fields
+    }};
+}
+
+#[derive(Debug, Default)]
+pub struct ArchiveDiff<'a> {
+    pub added: Vec<&'a Story>,
+    pub removed: Vec<&'a Story>,
+    pub updated: Vec<StoryChange<'a>>,
+}
+
+#[derive(Debug)]
+pub struct StoryChange<'a> {
+    pub old: &'a Story,
+    pub new: &'a Story,
+    pub fields: Vec<&'static str>,
+}
+
+fn compare(old: &Story, new: &Story) -> Vec<&'static str> {
+    changes! {
+        old, new,
+        "archive path" => archive.path,
+        "author" => author,
+        "chapters" => chapters,
+        "color" => color,
+        "completion status" => completion_status,
+        "content rating" => content_rating,
+        "cover image" => cover_image,
+        "date modified" => date_modified,
+        "date published" => date_published,
+        "date updated" => date_updated,
+        "description" => description_html,
+        "num chapters" => num_chapters,
+        "num comments" => num_comments,
+        "num dislikes" => num_dislikes,
+        "num likes" => num_likes,
+        "num views" => num_views,
+        "num words" => num_words,
+        "prequel" => prequel,
+        "published" => published,
+        "rating" => rating,
+        "short description" => short_description,
+        "status" => status,
+        "submitted" => submitted,
+        "tags" => tags,
+        "title" => title,
+        "total num views" => total_num_views,
+        "url" => url,
+    }
+}
+
+pub fn diff<'a, A, B>(
+    old: &'a Fetcher<A>,
+    new: &'a Fetcher<B>,
+) -> ArchiveDiff<'a>
+where
+    A: Read + Seek,
+    B: Read + Seek,
+{
+    let mut diff = ArchiveDiff::default();
+    let mut olds = old.iter().peekable();
+    let mut news = new.iter().peekable();
+
+    loop {
+        let order = match (olds.peek(), news.peek()) {
+            (Some(old), Some(new)) => old.id.cmp(&new.id),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+
+        match order {
+            Ordering::Less => diff.removed.extend(olds.next()),
+            Ordering::Greater => diff.added.extend(news.next()),
+            Ordering::Equal => {
+                let (Some(old), Some(new)) = (olds.next(), news.next()) else {
+                    break;
+                };
+
+                let fields = compare(old, new);
+
+                if !fields.is_empty() {
+                    diff.updated.push(StoryChange { old, new, fields });
+                }
+            }
+        }
+    }
+
+    diff
+}
@@ -0,0 +1,59 @@
+//! Story export.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use super::story::Story;
+
+const MAX_NAME: usize = 128;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Layout {
+    #[default]
+    AuthorTitle,
+    Id,
+    Flat,
+}
+
+pub fn sanitize(name: &str) -> String {
+    let mut clean = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => {
+                clean.push('_')
+            }
+            c if c.is_control() => clean.push('_'),
+            c => clean.push(c),
+        }
+
+        if clean.len() >= MAX_NAME {
+            break;
+        }
+    }
+
+    let clean = clean.trim_matches(|c: char| c == '.' || c.is_whitespace());
+
+    match clean.is_empty() {
+        true => String::from("_"),
+        false => clean.to_string(),
+    }
+}
+
+impl Layout {
+    pub fn path(&self, story: &Story) -> PathBuf {
+        let author = sanitize(&story.author.name);
+        let title = sanitize(&story.title);
+        let id = story.id;
+
+        match self {
+            Layout::AuthorTitle => {
+                Path::new(&author).join(format!("{title} ({id}).epub"))
+            }
+            Layout::Id => PathBuf::from(format!("{id}.epub")),
+            Layout::Flat => {
+                PathBuf::from(format!("{author} - {title} ({id}).epub"))
+            }
+        }
+    }
+}
@@ -1,117 +1,1042 @@
 //! Archive fetcher.
 
+use std::collections::HashMap;
+use std::fs::create_dir_all;
 use std::fs::File;
+use std::io::copy;
 use std::io::BufReader;
-use std::io::ErrorKind as IoErrorKind;
 use std::io::Read;
 use std::io::Seek;
+use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::result::Result as StdResult;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
 
+use flate2::read::GzDecoder;
 use rayon::prelude::*;
-use zip::read::ZipArchive;
-use zip::result::ZipError;
 
+use super::builder::FetcherBuilder;
+use super::builder::Options;
+use super::cache;
+use super::cancel::CancelToken;
+use super::cover::Cover;
+use super::export::Layout;
+use super::identity::ArchiveIdentity;
+use super::identity::Sha256;
+use super::indices::Indices;
+use super::indices::SortKey;
+use super::interner::InternerStats;
+use super::interner::Snapshot;
+use super::memory;
+use super::memory::MemoryUsage;
 use super::parser::parse;
+use super::parser::Duplicates;
+use super::plan::Lookup;
+use super::plan::QueryPlan;
+use super::random::Random;
+use super::registry;
+use super::registry::AuthorInfo;
+use super::registry::TagInfo;
+use super::remote::Remote;
+use super::schema::Schema;
+use super::storage::Entry;
+use super::storage::Storage;
+use super::story::Author;
 use super::story::Story;
+use super::story::StoryId;
+use super::story::Tag;
+use super::summary::StorySummary;
+use super::text;
+use super::validate;
+use super::validate::ValidationReport;
 use crate::archive::AUTHORS;
 use crate::archive::TAGS;
+use crate::epub::plain;
+use crate::epub::Epub;
+use crate::epub::Format;
+use crate::epub::Metadata;
 use crate::error::Error;
+use crate::error::ErrorBuilder;
+use crate::error::ErrorKind;
 use crate::error::Result;
 
+const BUDGET_CHUNK: usize = 1024;
+
+type Interned = (Snapshot<Author>, Snapshot<Tag>);
+
 pub struct Fetcher<T: Read + Seek> {
-    archive: Mutex<ZipArchive<T>>,
-    index: Vec<Story>,
+    storage: Storage<T>,
+    index: OnceLock<StdResult<Vec<Story>, Error>>,
+    diagnostics: OnceLock<Vec<(usize, Error)>>,
+    interned: OnceLock<Interned>,
+    indices: Indices,
+    options: Options,
+    random: Mutex<Random>,
+}
+
+fn random(options: &Options) -> Random {
+    match options.seed {
+        Some(seed) => Random::new(seed),
+        None => Random::from_time(),
+    }
 }
 
 impl Fetcher<BufReader<File>> {
     pub fn new(path: impl AsRef<Path>) -> Result<Self> {
-        use IoErrorKind::*;
+        FetcherBuilder::new().open(path)
+    }
 
-        let file = File::open(path).map_err(|e| match e.kind() {
-            NotFound => Error::archive("File not found"),
-            _ => Error::archive("Could not open file"),
-        })?;
+    pub fn with_cache(
+        path: impl AsRef<Path>,
+        cache: impl AsRef<Path>,
+    ) -> Result<Self> {
+        FetcherBuilder::new().cache(cache).open(path)
+    }
+}
 
-        Self::with_reader(BufReader::new(file))
+impl Fetcher<Remote> {
+    pub fn remote(url: &str) -> Result<Self> {
+        FetcherBuilder::new().remote(url)
     }
 }
 
 impl<T: Read + Seek> Fetcher<T> {
     pub fn with_reader(reader: T) -> Result<Self> {
-        let mut handle = Self::open(reader)?;
-        let index = Self::load(&mut handle)?;
-        let archive = Mutex::new(handle);
+        FetcherBuilder::new().reader(reader)
+    }
+
+    pub(crate) fn with_storage(
+        storage: Storage<T>,
+        options: Options,
+    ) -> Result<Self> {
+        let random = random(&options);
 
-        Ok(Self { archive, index })
+        let fetcher = Self {
+            storage,
+            index: OnceLock::new(),
+            diagnostics: OnceLock::new(),
+            interned: OnceLock::new(),
+            indices: Indices::default(),
+            options,
+            random: Mutex::new(random),
+        };
+
+        if !fetcher.options.lazy {
+            fetcher.load()?;
+        }
+
+        Ok(fetcher)
     }
 
-    fn open(archive: T) -> Result<ZipArchive<T>> {
-        use ZipError::*;
+    pub fn merge(fetchers: Vec<Self>) -> Result<Self> {
+        if fetchers.is_empty() {
+            return Err(Error::usage("No archives to merge"));
+        }
+
+        let options = fetchers[0].options.clone();
+        let mut storages = Vec::with_capacity(fetchers.len());
+        let mut stories = Vec::new();
+
+        for (origin, fetcher) in fetchers.into_iter().enumerate() {
+            fetcher.load()?;
+
+            let index = fetcher.index.into_inner().and_then(StdResult::ok);
+            let index = index.unwrap_or_default();
+            let entries = index.into_iter();
+            stories.extend(entries.map(|story| (origin, story)));
+            storages.push(fetcher.storage);
+        }
+
+        stories.sort_by_key(|(origin, story)| {
+            (story.id, story.archive.date_fetched, *origin)
+        });
 
-        ZipArchive::new(archive).map_err(|e| match e {
-            InvalidArchive(e) => Error::archive(e),
-            UnsupportedArchive(e) => Error::archive(e),
-            _ => Error::archive("Unknown ZIP-file issue"),
+        let mut newest: Vec<(usize, Story)> = Vec::new();
+
+        for (origin, story) in stories {
+            if newest.last().is_some_and(|(_, last)| last.id == story.id) {
+                newest.pop();
+            }
+
+            newest.push((origin, story));
+        }
+
+        let mut routes = HashMap::with_capacity(newest.len());
+        let mut index = Vec::with_capacity(newest.len());
+
+        for (origin, story) in newest {
+            routes.insert(Box::from(&*story.archive.path), origin);
+            index.push(story);
+        }
+
+        Ok(Self {
+            storage: Storage::merged(storages, routes),
+            index: OnceLock::from(Ok(index)),
+            diagnostics: OnceLock::new(),
+            interned: OnceLock::new(),
+            indices: Indices::default(),
+            random: Mutex::new(random(&options)),
+            options,
         })
     }
 
-    fn load(archive: &mut ZipArchive<T>) -> Result<Vec<Story>> {
-        use ZipError::*;
+    pub fn load(&self) -> Result<()> {
+        let index = self.index.get_or_init(|| match &self.options.cache {
+            Some(directory) => self.load_cached(directory),
+            None => self.load_index(),
+        });
+
+        match index {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error.copy()),
+        }
+    }
+
+    fn cache_key(&self) -> Result<ArchiveIdentity> {
+        let options = &self.options;
+        let mut hasher = Sha256::new();
+
+        let duplicates = match options.duplicates {
+            Duplicates::Error => 0,
+            Duplicates::KeepFirst => 1,
+            Duplicates::KeepLast => 2,
+        };
+
+        let schema = match options.schema {
+            None => 0,
+            Some(Schema::Current) => 1,
+            Some(Schema::Legacy) => 2,
+        };
+
+        hasher.update(self.identity()?.as_bytes());
+        hasher.update(&[duplicates, schema]);
+        hasher.update(&[options.strict as u8, options.skip_invalid as u8]);
+
+        Ok(hasher.finalize())
+    }
+
+    fn load_cached(&self, directory: &Path) -> Result<Vec<Story>> {
+        let key = self.cache_key()?;
+        let file = directory.join(format!("{key}.cache"));
+        let name = self.storage.index_name().unwrap_or_default();
 
-        let file = archive.by_name("index.json").map_err(|e| match e {
-            FileNotFound => Error::archive("Missing story index"),
-            _ => Error::archive("Could not open story index"),
-        })?;
+        let predicate = &self.options.predicate;
 
-        let reader = BufReader::with_capacity(1048576, file);
-        let result = parse(reader).map_err(Error::index);
+        if let Ok((mut index, diagnostics)) = cache::load(&file, &key) {
+            let diagnostics =
+                diagnostics.into_iter().map(|(line, message)| {
+                    let error = ErrorBuilder::new(ErrorKind::InvalidStory)
+                        .message(message)
+                        .entry(name)
+                        .line(line);
 
-        AUTHORS.clear();
-        TAGS.clear();
+                    (line, error.build())
+                });
+
+            let _ = self.diagnostics.set(diagnostics.collect());
+
+            if let Some(predicate) = predicate {
+                index.retain(|story| predicate(story));
+            }
+
+            return Ok(index);
+        }
+
+        let index = self.load_index()?;
+
+        if predicate.is_none() {
+            let diagnostics = self.diagnostics.get().into_iter().flatten();
+
+            let diagnostics: Vec<_> = diagnostics
+                .map(|(line, error)| {
+                    let message = error.message().cloned().unwrap_or_default();
+                    (*line, message.into_boxed_str())
+                })
+                .collect();
+
+            let _ = cache::save(&file, &key, &index, &diagnostics);
+        }
+
+        Ok(index)
+    }
+
+    fn load_index(&self) -> Result<Vec<Story>> {
+        let Some(name) = self.storage.index_name() else {
+            return Err(Error::archive("Missing story index"));
+        };
+
+        let Some(file) = self.storage.entry(name)? else {
+            return Err(Error::archive("Missing story index"));
+        };
+
+        let file: Box<dyn Read + '_> = match name.rsplit('.').next() {
+            Some("gz") => Box::new(GzDecoder::new(file)),
+            Some("zst") => {
+                return Err(Error::archive("Unsupported index compression"))
+            }
+            _ => Box::new(file),
+        };
+
+        let reader = BufReader::with_capacity(self.options.buffer, file);
+        let result =
+            parse(reader, &self.options).map_err(|e| e.with_entry(name));
+
+        let result = match (self.options.cancelled(), result) {
+            (true, _) => Err(Error::cancelled()),
+            (false, Err(error)) => Err(error),
+            (false, Ok((index, errors))) => {
+                let errors = errors.into_iter();
+                let errors =
+                    errors.map(|(line, e)| (line, e.with_entry(name)));
+                let _ = self.diagnostics.set(errors.collect());
+
+                Ok(index)
+            }
+        };
+
+        if !self.options.interned {
+            AUTHORS.clear();
+            TAGS.clear();
+            text::clear();
+        }
 
         result
     }
 
-    pub fn fetch(&self, key: i32) -> Option<&Story> {
-        match self.index.binary_search_by_key(&key, |story| story.id) {
-            Ok(i) => self.index.get(i),
+    fn index(&self) -> &[Story] {
+        let _ = self.load();
+
+        match self.index.get() {
+            Some(Ok(index)) => index,
+            _ => &[],
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[(usize, Error)] {
+        let _ = self.index();
+
+        self.diagnostics
+            .get()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn identity(&self) -> Result<ArchiveIdentity> {
+        self.storage.identity()
+    }
+
+    fn interned(&self) -> &Interned {
+        let index = self.index();
+
+        self.interned.get_or_init(|| {
+            let authors = index.iter().map(|story| &story.author);
+            let tags = index.iter().flat_map(|story| story.tags.iter());
+
+            (Snapshot::new(authors), Snapshot::new(tags))
+        })
+    }
+
+    pub fn author_stats(&self) -> InternerStats {
+        self.interned().0.stats
+    }
+
+    pub fn tag_stats(&self) -> InternerStats {
+        self.interned().1.stats
+    }
+
+    pub fn interned_authors(&self) -> impl Iterator<Item = &Author> {
+        self.interned().0.values.iter().map(AsRef::as_ref)
+    }
+
+    pub fn interned_tags(&self) -> impl Iterator<Item = &Tag> {
+        self.interned().1.values.iter().map(AsRef::as_ref)
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        memory::usage(self.index(), self.indices.memory())
+    }
+
+    pub fn fetch(&self, key: impl Into<StoryId>) -> Option<&Story> {
+        let index = self.index();
+        let key = key.into();
+
+        match index.binary_search_by_key(&key, |story| story.id) {
+            Ok(i) => index.get(i),
             Err(_) => None,
         }
     }
 
-    pub fn read(&self, story: &Story) -> Result<Vec<u8>> {
-        use ZipError::*;
+    pub fn fetch_by_url(&self, url: &str) -> Option<&Story> {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let mut segments = path.split('/').skip_while(|s| *s != "story");
+
+        let id: StoryId = segments.nth(1)?.parse().ok()?;
+
+        self.fetch(id)
+    }
+
+    fn lookup(&self, positions: &[usize]) -> Vec<&Story> {
+        let index = self.index();
+
+        positions.iter().map(|&i| &index[i]).collect()
+    }
+
+    pub fn by_author(&self, author: i32) -> Vec<&Story> {
+        self.lookup(self.indices.author(self.index(), author))
+    }
+
+    pub fn by_tag(&self, tag: i32) -> Vec<&Story> {
+        self.lookup(self.indices.tag(self.index(), tag))
+    }
+
+    pub fn sequels(&self, story: &Story) -> Vec<&Story> {
+        self.lookup(self.indices.sequels(self.index(), story.id))
+    }
+
+    pub fn has_sequel(&self, story: &Story) -> bool {
+        !self.indices.sequels(self.index(), story.id).is_empty()
+    }
+
+    pub fn prequels(&self) -> impl Iterator<Item = StoryId> + '_ {
+        self.indices.prequels(self.index())
+    }
+
+    pub fn sorted_by(&self, key: SortKey) -> Vec<&Story> {
+        self.lookup(self.indices.sorted(self.index(), key))
+    }
+
+    pub fn authors(&self) -> Vec<AuthorInfo> {
+        registry::authors(self.index())
+    }
+
+    pub fn tags(&self) -> Vec<TagInfo> {
+        registry::tags(self.index())
+    }
+
+    pub fn summaries(&self) -> Vec<StorySummary> {
+        self.index().iter().map(StorySummary::from).collect()
+    }
+
+    pub fn into_summaries(self) -> Vec<StorySummary> {
+        let _ = self.load();
+
+        let index = self.index.into_inner().and_then(StdResult::ok);
+        index
+            .unwrap_or_default()
+            .into_iter()
+            .map(StorySummary::from)
+            .collect()
+    }
+
+    pub fn find_title(&self, title: &str) -> Vec<&Story> {
+        self.lookup(self.indices.title(self.index(), title.trim()))
+    }
 
+    pub fn find_tag(&self, name: &str) -> Vec<&Story> {
+        self.lookup(self.indices.tag_name(self.index(), name.trim()))
+    }
+
+    fn entry(&self, story: &Story) -> Result<Entry<T>> {
         let path = &story.archive.path;
+        let entry = self.storage.entry(path);
 
-        let Ok(mut archive) = self.archive.lock() else {
-            return Err(Error::archive("Could not acquire fetcher lock"));
-        };
+        match entry.map_err(|e| e.with_story(story.id))? {
+            Some(entry) => Ok(entry),
+            None => Err(ErrorBuilder::new(ErrorKind::ArchiveError)
+                .message("Missing story data")
+                .story(story.id)
+                .entry(path)
+                .build()),
+        }
+    }
+
+    pub fn open(&self, story: &Story) -> Result<impl Read> {
+        self.entry(story)
+    }
+
+    pub fn read(&self, story: &Story) -> Result<Vec<u8>> {
+        let mut entry = self.entry(story)?;
+        let mut buf = Vec::with_capacity(entry.size() as usize);
+
+        if let Err(e) = entry.read_to_end(&mut buf) {
+            return Err(ErrorBuilder::new(ErrorKind::ArchiveError)
+                .message("Could not read story data")
+                .story(story.id)
+                .entry(&story.archive.path)
+                .source(e)
+                .build());
+        }
 
-        let mut file = archive.by_name(path).map_err(|e| match e {
-            FileNotFound => Error::archive("Missing story data"),
-            _ => Error::archive("Could not open story data"),
-        })?;
+        Ok(buf)
+    }
+
+    pub fn read_path(&self, path: &str) -> Result<Vec<u8>> {
+        if path.starts_with('/') || path.split('/').any(|part| part == "..") {
+            return Err(Error::usage("Invalid archive entry path"));
+        }
 
-        let size = file.size() as usize;
-        let mut buf = Vec::with_capacity(size);
+        let entry = self.storage.entry(path).map_err(|e| e.with_entry(path));
 
-        let Ok(_) = file.read_to_end(&mut buf) else {
-            return Err(Error::archive("Could not read story data"));
+        let Some(mut entry) = entry? else {
+            return Err(
+                Error::archive("Missing archive entry").with_entry(path)
+            );
         };
 
+        let mut buf = Vec::with_capacity(entry.size() as usize);
+
+        if let Err(e) = entry.read_to_end(&mut buf) {
+            return Err(ErrorBuilder::new(ErrorKind::ArchiveError)
+                .message("Could not read archive entry")
+                .entry(path)
+                .source(e)
+                .build());
+        }
+
         Ok(buf)
     }
 
+    pub fn epub(&self, story: &Story) -> Result<Epub> {
+        Epub::parse(self.read(story)?).map_err(|e| e.with_story(story.id))
+    }
+
+    pub fn epub_metadata(&self, story: &Story) -> Result<Metadata> {
+        Ok(self.epub(story)?.metadata().clone())
+    }
+
+    pub fn validate(&self, story: &Story) -> Result<ValidationReport> {
+        let epub = Epub::parse(self.read(story)?);
+
+        Ok(validate::validate(story, epub.map_err(|e| e.to_string())))
+    }
+
+    pub fn cover(&self, story: &Story) -> Result<Option<Cover>> {
+        let epub = Epub::parse(self.read(story)?);
+        let embedded = epub.ok().and_then(|e| e.cover_image().ok().flatten());
+
+        if let Some(image) = embedded {
+            return Ok(Some(Cover::Embedded(image)));
+        }
+
+        let url = story.cover_image.as_ref().map(|cover| &cover.full);
+
+        Ok(url.map(|url| Cover::Url(url.clone())))
+    }
+
+    pub fn chapter_text(
+        &self,
+        story: &Story,
+        chapter: usize,
+        format: Format,
+    ) -> Result<String> {
+        let epub = self.epub(story)?;
+
+        let Some(item) = epub.chapters().get(chapter).copied() else {
+            return Err(Error::usage("Chapter index out of range"));
+        };
+
+        let html = epub.read_text(item)?;
+
+        match format {
+            Format::Html => Ok(html),
+            Format::Plain => Ok(plain(&html)),
+        }
+    }
+
+    pub fn write_text<W: Write>(
+        &self,
+        story: &Story,
+        mut writer: W,
+    ) -> Result<()> {
+        let epub = self.epub(story)?;
+
+        for (i, item) in epub.chapters().into_iter().enumerate() {
+            let text = plain(&epub.read_text(item)?);
+            let separator = if i == 0 { "" } else { "\n\n" };
+
+            if let Err(e) = write!(writer, "{separator}{text}") {
+                let error = ErrorBuilder::new(ErrorKind::ArchiveError)
+                    .message("Could not write story text")
+                    .story(story.id)
+                    .source(e);
+
+                return Err(error.build());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn story_text(&self, story: &Story) -> Result<String> {
+        let mut buf = Vec::new();
+
+        self.write_text(story, &mut buf)?;
+
+        match String::from_utf8(buf) {
+            Ok(text) => Ok(text),
+            Err(_) => Err(Error::invalid()),
+        }
+    }
+
+    pub fn read_many(&self, stories: &[&Story]) -> Vec<Result<Vec<u8>>>
+    where
+        T: Send,
+    {
+        self.install(|| stories.par_iter().map(|s| self.read(s)).collect())
+    }
+
+    pub fn export(
+        &self,
+        stories: &[&Story],
+        dest: impl AsRef<Path>,
+        layout: Layout,
+    ) -> Result<Vec<PathBuf>> {
+        let dest = dest.as_ref();
+        let mut paths = Vec::with_capacity(stories.len());
+
+        for story in stories {
+            let path = dest.join(layout.path(story));
+
+            if let Some(parent) = path.parent() {
+                if let Err(e) = create_dir_all(parent) {
+                    let error = ErrorBuilder::new(ErrorKind::ArchiveError)
+                        .message("Could not create directory")
+                        .path(parent.display())
+                        .source(e);
+
+                    return Err(error.build());
+                }
+            }
+
+            let mut entry = self.entry(story)?;
+
+            let mut file = match File::create(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    let error = ErrorBuilder::new(ErrorKind::ArchiveError)
+                        .message("Could not create export file")
+                        .path(path.display())
+                        .story(story.id)
+                        .source(e);
+
+                    return Err(error.build());
+                }
+            };
+
+            if let Err(e) = copy(&mut entry, &mut file) {
+                let error = ErrorBuilder::new(ErrorKind::ArchiveError)
+                    .message("Could not export story data")
+                    .path(path.display())
+                    .story(story.id)
+                    .source(e);
+
+                return Err(error.build());
+            }
+
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+
+    fn install<F, R>(&self, function: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match &self.options.pool {
+            Some(pool) => pool.install(function),
+            None => function(),
+        }
+    }
+
+    pub fn stories(&self) -> &[Story] {
+        self.index()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index().is_empty()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Story> {
-        self.index.iter()
+        self.index().iter()
     }
 
     pub fn filter<F>(&self, function: &F) -> Vec<&Story>
     where
         F: Sync + Fn(&Story) -> bool,
     {
-        self.index.par_iter().filter(|s| function(s)).collect()
+        let index = self.index();
+
+        self.install(|| index.par_iter().filter(|s| function(s)).collect())
+    }
+
+    pub fn query<F>(&self, function: &F, plan: &QueryPlan) -> Vec<&Story>
+    where
+        F: Sync + Fn(&Story) -> bool,
+    {
+        let offset = plan.offset;
+
+        let mut stories = match (&plan.lookup, plan.sort, plan.limit) {
+            (Some(lookup), _, _) => {
+                let stories = self.candidates(lookup).into_iter();
+                stories.filter(|s| function(s)).collect()
+            }
+            (None, None, Some(limit)) => {
+                let stories = self.index().iter().filter(|s| function(s));
+                return stories.skip(offset).take(limit).collect();
+            }
+            (None, _, _) => self.filter(function),
+        };
+
+        if let Some((key, order)) = plan.sort {
+            key.sort(&mut stories, order);
+        }
+
+        if let Some(limit) = plan.limit {
+            stories.truncate(offset.saturating_add(limit));
+        }
+
+        stories.drain(..offset.min(stories.len()));
+
+        stories
+    }
+
+    fn candidates(&self, lookup: &Lookup) -> Vec<&Story> {
+        match lookup {
+            Lookup::Story(id) => self.fetch(*id).into_iter().collect(),
+            Lookup::Author(id) => self.by_author(*id),
+            Lookup::Tag(name) => self.find_tag(name),
+            Lookup::Sequels(id) => {
+                self.lookup(self.indices.sequels(self.index(), *id))
+            }
+        }
+    }
+
+    pub fn sample(&self, count: usize) -> Vec<&Story> {
+        self.sample_where(&|_| true, count)
+    }
+
+    pub fn sample_where<F>(&self, function: &F, count: usize) -> Vec<&Story>
+    where
+        F: Fn(&Story) -> bool,
+    {
+        let stories = self.index().iter().filter(|s| function(s));
+        let mut random = match self.random.lock() {
+            Ok(random) => random,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        random.sample(stories, count)
+    }
+
+    pub fn sample_seeded<F>(
+        &self,
+        function: &F,
+        count: usize,
+        seed: u64,
+    ) -> Vec<&Story>
+    where
+        F: Fn(&Story) -> bool,
+    {
+        let stories = self.index().iter().filter(|s| function(s));
+
+        Random::new(seed).sample(stories, count)
+    }
+
+    pub fn filter_cancellable<F>(
+        &self,
+        function: &F,
+        token: &CancelToken,
+    ) -> Result<Vec<&Story>>
+    where
+        F: Sync + Fn(&Story) -> bool,
+    {
+        let index = self.index();
+
+        let stories = self.install(|| {
+            index
+                .par_iter()
+                .filter(|s| !token.is_cancelled() && function(s))
+                .collect()
+        });
+
+        match token.is_cancelled() {
+            true => Err(Error::cancelled()),
+            false => Ok(stories),
+        }
+    }
+
+    pub fn filter_within<F>(
+        &self,
+        function: &F,
+        budget: Duration,
+    ) -> Result<Vec<&Story>>
+    where
+        F: Sync + Fn(&Story) -> bool,
+    {
+        let index = self.index();
+        let deadline = Instant::now() + budget;
+        let expired = AtomicBool::new(false);
+
+        let stories = self.install(|| {
+            index
+                .par_chunks(BUDGET_CHUNK)
+                .flat_map_iter(|chunk| {
+                    if Instant::now() >= deadline {
+                        expired.store(true, Ordering::Relaxed);
+                    }
+
+                    let chunk = match expired.load(Ordering::Relaxed) {
+                        true => &chunk[..0],
+                        false => chunk,
+                    };
+
+                    chunk.iter().filter(|s| function(s))
+                })
+                .collect()
+        });
+
+        match expired.into_inner() {
+            false => Ok(stories),
+            true => Err(ErrorBuilder::new(ErrorKind::Cancelled)
+                .message("Filter exceeded its time budget")
+                .build()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::env::temp_dir;
+    use std::fs::create_dir_all;
+    use std::fs::write;
+    use std::path::PathBuf;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::Fetcher;
+    use super::FetcherBuilder;
+    use super::Layout;
+    use super::QueryPlan;
+    use super::SortKey;
+    use super::Story;
+    use crate::archive::story::tests::STORY;
+    use crate::archive::Order;
+    use crate::error::ErrorKind;
+
+    pub(crate) fn line(id: i32, fields: &str) -> String {
+        let story = STORY.replacen("\"id\": 1,", &format!("\"id\": {id},"), 1);
+
+        match fields.is_empty() {
+            true => format!("\"{id}\": {story}"),
+            false => format!("\"{id}\": {{{fields}, {}", &story[1..]),
+        }
+    }
+
+    pub(crate) fn archive(name: &str, lines: &[String]) -> PathBuf {
+        let pid = std::process::id();
+        let path = temp_dir().join(format!("fimfareader-{name}-{pid}"));
+        let index = format!("{{\n{}\n}}\n", lines.join(",\n"));
+
+        create_dir_all(&path).unwrap();
+        write(path.join("index.json"), index).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn keys_cache_by_options() {
+        let path = archive("key", &[line(1, "")]);
+
+        let key = |strict| {
+            let builder = FetcherBuilder::new().lazy(true).strict(strict);
+            builder.open(&path).unwrap().cache_key().unwrap()
+        };
+
+        assert_eq!(key(true), key(true));
+        assert_ne!(key(true), key(false));
+    }
+
+    #[test]
+    fn merges_newest_stories_first() {
+        let fetched = |line: String, date: &str, title: &str| {
+            let key = "\"date_fetched\": \"2020-01-01T00:00:00+00:00\"";
+            let date = format!("\"date_fetched\": \"{date}\"");
+
+            line.replace(key, &date).replace("Story number 1", title)
+        };
+
+        let old = "2019-01-01T00:00:00+00:00";
+        let new = "2021-01-01T00:00:00+00:00";
+
+        let first = [
+            fetched(line(1, ""), old, "first"),
+            fetched(line(2, ""), new, "first"),
+            fetched(line(3, ""), old, "first"),
+        ];
+
+        let second = [
+            fetched(line(2, ""), old, "second"),
+            fetched(line(3, ""), old, "second"),
+            fetched(line(4, ""), new, "second"),
+        ];
+
+        let first = archive("merge-first", &first);
+        let second = archive("merge-second", &second);
+
+        let open = |path| FetcherBuilder::new().open(path).unwrap();
+        let merged = Fetcher::merge(vec![open(&first), open(&second)]);
+        let merged = merged.unwrap();
+
+        let titles: Vec<_> = merged
+            .stories()
+            .iter()
+            .map(|story| (story.id.0, &*story.title))
+            .collect();
+
+        assert_eq!(
+            titles,
+            [(1, "first"), (2, "first"), (3, "second"), (4, "second")],
+        );
+    }
+
+    #[test]
+    fn counts_interned_values_per_fetcher() {
+        let path = archive("stats", &[line(1, ""), line(2, "")]);
+        let cache = path.join("cache");
+
+        let open = || {
+            let builder = FetcherBuilder::new().cache(&cache);
+            builder.open(&path).unwrap()
+        };
+
+        let (fresh, cached) = (open(), open());
+        let merged = Fetcher::merge(vec![open(), open()]).unwrap();
+
+        for fetcher in [&fresh, &cached, &merged] {
+            let authors = fetcher.author_stats();
+            let tags = fetcher.tag_stats();
+
+            assert_eq!((authors.values, authors.hits), (1, 1));
+            assert_eq!((tags.values, tags.hits), (2, 2));
+            assert_eq!(fetcher.interned_tags().count(), 2);
+        }
+    }
+
+    #[test]
+    fn reports_export_failures_with_paths() {
+        let path = archive("export", &[line(1, "")]);
+        let blocked = path.join("blocked");
+        write(&blocked, "").unwrap();
+
+        let fetcher = FetcherBuilder::new().open(&path).unwrap();
+        let stories: Vec<_> = fetcher.stories().iter().collect();
+
+        let Err(error) = fetcher.export(&stories, &blocked, Layout::Flat)
+        else {
+            panic!("Exported into a file");
+        };
+
+        assert!(matches!(error.kind(), ErrorKind::ArchiveError));
+        assert!(error.context().path.is_some());
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn keeps_results_finished_after_deadline() {
+        let path = archive("budget", &[line(1, ""), line(2, "")]);
+        let fetcher = FetcherBuilder::new().open(&path).unwrap();
+        let budget = Duration::from_millis(10);
+
+        let slow = |_: &Story| {
+            sleep(budget * 2);
+            true
+        };
+
+        assert_eq!(fetcher.filter_within(&slow, budget).unwrap().len(), 2);
+        assert!(fetcher.filter_within(&|_| true, Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn finds_tags_by_folded_name() {
+        let tag = r#"{"id": 2, "name": "Adventure""#;
+        let cafe = r#"{"id": 9002, "name": "Cafe\u0301""#;
+        let lines = [line(1, ""), line(2, "").replace(tag, cafe)];
+
+        let path = archive("fold", &lines);
+        let fetcher = FetcherBuilder::new().open(&path).unwrap();
+
+        let ids = |name| -> Vec<i32> {
+            fetcher.find_tag(name).iter().map(|s| s.id.0).collect()
+        };
+
+        assert_eq!(ids("cafe"), [2]);
+        assert_eq!(ids("CAF\u{c9}"), [2]);
+        assert_eq!(ids("adventure"), [1]);
+    }
+
+    fn ranked(name: &str) -> PathBuf {
+        let liked = |id, likes: i32| {
+            let likes = format!("\"num_likes\": {likes}");
+            line(id, "").replace("\"num_likes\": 3", &likes)
+        };
+
+        archive(name, &[liked(1, 20), liked(2, 40), liked(3, 10)])
+    }
+
+    #[test]
+    fn sorts_query_results() {
+        let path = ranked("sort");
+        let fetcher = FetcherBuilder::new().open(&path).unwrap();
+
+        let ids = |order| -> Vec<i32> {
+            let plan = QueryPlan {
+                sort: Some((SortKey::Likes, order)),
+                ..QueryPlan::default()
+            };
+
+            let stories = fetcher.query(&|_| true, &plan);
+            stories.iter().map(|s| s.id.0).collect()
+        };
+
+        assert_eq!(ids(Order::Ascending), [3, 1, 2]);
+        assert_eq!(ids(Order::Descending), [2, 1, 3]);
+    }
+
+    #[test]
+    fn pages_query_results() {
+        let path = ranked("page");
+        let fetcher = FetcherBuilder::new().open(&path).unwrap();
+
+        let ids = |sort, offset, limit| -> Vec<i32> {
+            let plan = QueryPlan {
+                sort,
+                offset,
+                limit,
+                ..QueryPlan::default()
+            };
+
+            let stories = fetcher.query(&|_| true, &plan);
+            stories.iter().map(|s| s.id.0).collect()
+        };
+
+        let likes = Some((SortKey::Likes, Order::Descending));
+
+        assert_eq!(ids(likes, 1, Some(1)), [1]);
+        assert_eq!(ids(likes, 1, None), [1, 3]);
+        assert_eq!(ids(likes, 5, Some(1)), [] as [i32; 0]);
+        assert_eq!(ids(None, 1, Some(1)), [2]);
+        assert_eq!(ids(None, 0, Some(2)), [1, 2]);
     }
 }
@@ -0,0 +1,166 @@
+//! Shared reader handle.
+
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+
+type Opener<T> = Box<dyn Fn() -> IoResult<T> + Send + Sync>;
+
+struct Reader<T> {
+    inner: T,
+    offset: Option<u64>,
+}
+
+struct Pool<T> {
+    readers: Mutex<Vec<Reader<T>>>,
+    returned: Condvar,
+    opener: Option<Opener<T>>,
+}
+
+pub struct Handle<T> {
+    pool: Arc<Pool<T>>,
+    offset: u64,
+}
+
+impl<T: Read + Seek> Handle<T> {
+    pub fn new(reader: T) -> IoResult<Self> {
+        Self::with_pool(reader, None)
+    }
+
+    pub fn with_opener<F>(reader: T, opener: F) -> IoResult<Self>
+    where
+        F: Fn() -> IoResult<T> + Send + Sync + 'static,
+    {
+        Self::with_pool(reader, Some(Box::new(opener)))
+    }
+
+    fn with_pool(mut inner: T, opener: Option<Opener<T>>) -> IoResult<Self> {
+        let offset = inner.stream_position()?;
+
+        let reader = Reader {
+            inner,
+            offset: Some(offset),
+        };
+
+        let pool = Pool {
+            readers: Mutex::new(vec![reader]),
+            returned: Condvar::new(),
+            opener,
+        };
+
+        Ok(Self {
+            pool: Arc::new(pool),
+            offset,
+        })
+    }
+
+    fn checkout(&self) -> IoResult<Reader<T>> {
+        let error = || IoError::other("Could not acquire reader lock");
+        let mut readers = self.pool.readers.lock().map_err(|_| error())?;
+
+        loop {
+            let offset = Some(self.offset);
+            let found = readers.iter().position(|r| r.offset == offset);
+
+            if let Some(i) = found {
+                return Ok(readers.swap_remove(i));
+            }
+
+            if let Some(reader) = readers.pop() {
+                return Ok(reader);
+            }
+
+            if let Some(opener) = &self.pool.opener {
+                drop(readers);
+
+                return Ok(Reader {
+                    inner: opener()?,
+                    offset: None,
+                });
+            }
+
+            readers = self.pool.returned.wait(readers).map_err(|_| error())?;
+        }
+    }
+
+    fn checkin(&self, reader: Reader<T>) {
+        if let Ok(mut readers) = self.pool.readers.lock() {
+            readers.push(reader);
+            self.pool.returned.notify_one();
+        }
+    }
+
+    fn access<F, R>(&self, function: F) -> IoResult<R>
+    where
+        F: FnOnce(&mut Reader<T>) -> IoResult<R>,
+    {
+        let mut reader = self.checkout()?;
+        let result = function(&mut reader);
+        self.checkin(reader);
+
+        result
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            offset: self.offset,
+        }
+    }
+}
+
+impl<T: Read + Seek> Read for Handle<T> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let offset = self.offset;
+
+        let count = self.access(|reader| {
+            if reader.offset != Some(offset) {
+                reader.offset = None;
+                reader.inner.seek(SeekFrom::Start(offset))?;
+            }
+
+            reader.offset = None;
+            let count = reader.inner.read(buf)?;
+            reader.offset = Some(offset + count as u64);
+
+            Ok(count)
+        })?;
+
+        self.offset += count as u64;
+
+        Ok(count)
+    }
+}
+
+impl<T: Read + Seek> Seek for Handle<T> {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+            SeekFrom::End(_) => Some(self.access(|reader| {
+                reader.offset = None;
+                let offset = reader.inner.seek(pos)?;
+                reader.offset = Some(offset);
+
+                Ok(offset)
+            })?),
+        };
+
+        let Some(offset) = offset else {
+            let kind = IoErrorKind::InvalidInput;
+            return Err(IoError::new(kind, "Invalid seek position"));
+        };
+
+        self.offset = offset;
+
+        Ok(offset)
+    }
+}
@@ -0,0 +1,192 @@
+//! Secondary indices.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::OnceLock;
+
+use super::memory::Heap;
+use super::story::Story;
+use super::story::StoryId;
+use crate::unicode::fold;
+
+type Positions<K> = HashMap<K, Box<[usize]>>;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SortKey {
+    Title,
+    Words,
+    Likes,
+    Published,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Order {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Default)]
+pub struct Indices {
+    authors: OnceLock<Positions<i32>>,
+    tags: OnceLock<Positions<i32>>,
+    tag_names: OnceLock<Positions<Box<str>>>,
+    titles: OnceLock<Positions<Box<str>>>,
+    sequels: OnceLock<Positions<StoryId>>,
+    sorted: [OnceLock<Box<[usize]>>; 4],
+}
+
+fn group<K, I, F>(stories: &[Story], function: F) -> Positions<K>
+where
+    K: Eq + Hash,
+    I: IntoIterator<Item = K>,
+    F: Fn(&Story) -> I,
+{
+    let mut groups: HashMap<K, Vec<usize>> = HashMap::new();
+
+    for (i, story) in stories.iter().enumerate() {
+        for key in function(story) {
+            groups.entry(key).or_default().push(i);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, positions)| (key, positions.into_boxed_slice()))
+        .collect()
+}
+
+fn order(stories: &[Story], key: SortKey) -> Box<[usize]> {
+    let mut positions: Vec<usize> = (0..stories.len()).collect();
+
+    match key {
+        SortKey::Title => positions.sort_by_cached_key(|&i| {
+            (stories[i].title.to_lowercase(), stories[i].id)
+        }),
+        SortKey::Words => {
+            positions.sort_by_key(|&i| (stories[i].num_words, stories[i].id))
+        }
+        SortKey::Likes => {
+            positions.sort_by_key(|&i| (stories[i].num_likes, stories[i].id))
+        }
+        SortKey::Published => positions
+            .sort_by_key(|&i| (stories[i].date_published, stories[i].id)),
+    }
+
+    positions.into_boxed_slice()
+}
+
+fn key(name: &str) -> Box<str> {
+    fold(name).to_lowercase().into()
+}
+
+fn find<'a, K: Eq + Hash>(
+    positions: &'a Positions<K>,
+    key: &K,
+) -> &'a [usize] {
+    match positions.get(key) {
+        Some(positions) => positions,
+        None => &[],
+    }
+}
+
+impl SortKey {
+    pub fn sort(self, stories: &mut [&Story], order: Order) {
+        match self {
+            SortKey::Title => {
+                stories.sort_by_cached_key(|s| (s.title.to_lowercase(), s.id))
+            }
+            SortKey::Words => stories.sort_by_key(|s| (s.num_words, s.id)),
+            SortKey::Likes => stories.sort_by_key(|s| (s.num_likes, s.id)),
+            SortKey::Published => {
+                stories.sort_by_key(|s| (s.date_published, s.id))
+            }
+        }
+
+        if order == Order::Descending {
+            stories.reverse();
+        }
+    }
+}
+
+impl Indices {
+    pub fn author(&self, stories: &[Story], id: i32) -> &[usize] {
+        let authors = self
+            .authors
+            .get_or_init(|| group(stories, |story| [story.author.id]));
+
+        find(authors, &id)
+    }
+
+    pub fn tag(&self, stories: &[Story], id: i32) -> &[usize] {
+        let tags = self.tags.get_or_init(|| {
+            group(stories, |story| {
+                story.tags.iter().map(|tag| tag.id).collect::<Vec<_>>()
+            })
+        });
+
+        find(tags, &id)
+    }
+
+    pub fn tag_name(&self, stories: &[Story], name: &str) -> &[usize] {
+        let names = self.tag_names.get_or_init(|| {
+            group(stories, |story| {
+                let tags = story.tags.iter();
+                let mut names: Vec<Box<str>> =
+                    tags.map(|tag| key(&tag.name)).collect();
+
+                names.sort_unstable();
+                names.dedup();
+                names
+            })
+        });
+
+        find(names, &key(name))
+    }
+
+    pub fn title(&self, stories: &[Story], title: &str) -> &[usize] {
+        let titles = self.titles.get_or_init(|| {
+            group(stories, |story| [story.title.to_lowercase().into()])
+        });
+
+        find(titles, &title.to_lowercase().into())
+    }
+
+    fn sequel_map(&self, stories: &[Story]) -> &Positions<StoryId> {
+        self.sequels
+            .get_or_init(|| group(stories, |story| story.prequel))
+    }
+
+    pub fn sequels(&self, stories: &[Story], id: StoryId) -> &[usize] {
+        find(self.sequel_map(stories), &id)
+    }
+
+    pub fn prequels(
+        &self,
+        stories: &[Story],
+    ) -> impl Iterator<Item = StoryId> + '_ {
+        self.sequel_map(stories).keys().copied()
+    }
+
+    pub fn memory(&self) -> usize {
+        let authors = self.authors.get().map(Heap::heap);
+        let tags = self.tags.get().map(Heap::heap);
+        let tag_names = self.tag_names.get().map(Heap::heap);
+        let titles = self.titles.get().map(Heap::heap);
+        let sequels = self.sequels.get().map(Heap::heap);
+        let sorted = self.sorted.iter().filter_map(|s| s.get());
+
+        authors.unwrap_or_default()
+            + tags.unwrap_or_default()
+            + tag_names.unwrap_or_default()
+            + titles.unwrap_or_default()
+            + sequels.unwrap_or_default()
+            + sorted.map(Heap::heap).sum::<usize>()
+    }
+
+    pub fn sorted(&self, stories: &[Story], key: SortKey) -> &[usize] {
+        let slot = &self.sorted[key as usize];
+
+        slot.get_or_init(|| order(stories, key))
+    }
+}
@@ -8,6 +8,27 @@ use std::sync::RwLock;
 
 pub struct Interner<T>(RwLock<HashSet<Arc<T>>>);
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InternerStats {
+    pub values: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+pub(crate) struct Snapshot<T> {
+    pub stats: InternerStats,
+    pub values: Vec<Arc<T>>,
+}
+
+impl InternerStats {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
 impl<T> Interner<T>
 where
     T: Eq + Hash,
@@ -43,6 +64,35 @@ where
     }
 }
 
+impl<T> Snapshot<T>
+where
+    T: Eq + Hash,
+{
+    pub fn new<'a, I>(references: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Arc<T>>,
+        T: 'a,
+    {
+        let mut distinct = HashSet::new();
+        let mut total = 0;
+
+        for reference in references {
+            distinct.insert(reference);
+            total += 1;
+        }
+
+        let values: Vec<Arc<T>> = distinct.into_iter().cloned().collect();
+
+        let stats = InternerStats {
+            values: values.len(),
+            hits: total - values.len(),
+            misses: values.len(),
+        };
+
+        Self { stats, values }
+    }
+}
+
 impl<T> Default for Interner<T>
 where
     T: Eq + Hash,
@@ -0,0 +1,181 @@
+//! Lenient deserializer.
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use log::warn;
+use serde::de::value::MapDeserializer;
+use serde::de::value::SeqDeserializer;
+use serde::de::DeserializeOwned;
+use serde::de::IntoDeserializer;
+use serde::de::Visitor;
+use serde::forward_to_deserialize_any;
+use serde::Deserializer;
+use serde_json::error::Error;
+use serde_json::error::Result;
+use serde_json::Value;
+
+static REPORTED: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(Default::default);
+
+struct Lenient(Value);
+
+#[cfg(not(feature = "raw-index"))]
+pub fn from_str<T: DeserializeOwned>(json: &str) -> Result<T> {
+    T::deserialize(Lenient(serde_json::from_str(json)?))
+}
+
+#[cfg(feature = "raw-index")]
+pub fn from_slice<T: DeserializeOwned>(json: &[u8]) -> Result<T> {
+    T::deserialize(Lenient(serde_json::from_slice(json)?))
+}
+
+fn report(name: &str, field: &str) {
+    let Ok(mut reported) = REPORTED.lock() else {
+        return;
+    };
+
+    if reported.insert(format!("{name}.{field}")) {
+        warn!("Ignoring unknown field `{field}` in {name}");
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Lenient {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Lenient {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(value) => visitor.visit_bool(value),
+            Value::Number(value) => {
+                if let Some(value) = value.as_u64() {
+                    visitor.visit_u64(value)
+                } else if let Some(value) = value.as_i64() {
+                    visitor.visit_i64(value)
+                } else {
+                    visitor.visit_f64(value.as_f64().unwrap_or_default())
+                }
+            }
+            Value::String(value) => visitor.visit_string(value),
+            Value::Array(values) => {
+                let values = values.into_iter().map(Lenient);
+                visitor.visit_seq(SeqDeserializer::new(values))
+            }
+            Value::Object(map) => {
+                let entries = map.into_iter().map(|(k, v)| (k, Lenient(v)));
+                visitor.visit_map(MapDeserializer::new(entries))
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.0 {
+            Value::String(value) => {
+                visitor.visit_enum(value.into_deserializer())
+            }
+            value => value.deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let Value::Object(map) = self.0 else {
+            return self.deserialize_any(visitor);
+        };
+
+        let entries = map.into_iter().filter_map(|(k, v)| {
+            if fields.contains(&k.as_str()) {
+                return Some((k, Lenient(v)));
+            }
+
+            report(name, &k);
+
+            None
+        });
+
+        visitor.visit_map(MapDeserializer::new(entries))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::fetcher::tests::archive;
+    use crate::archive::fetcher::tests::line;
+    use crate::archive::story::tests::story;
+    use crate::archive::FetcherBuilder;
+    use crate::archive::StoryId;
+
+    fn unknown() -> String {
+        let nested = r#""avatar": null, "pronouns": "they""#;
+        line(1, r#""badge": 1"#).replace(r#""avatar": null"#, nested)
+    }
+
+    #[test]
+    fn ignores_unknown_fields() {
+        let path = archive("lenient", &[unknown()]);
+        let builder = FetcherBuilder::new().strict(false);
+        let fetcher = builder.open(&path).unwrap();
+
+        assert_eq!(fetcher.fetch(StoryId(1)), Some(&story(1)));
+        assert!(fetcher.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_fields_when_strict() {
+        let path = archive("strict", &[line(2, ""), unknown()]);
+
+        let Err(error) = FetcherBuilder::new().strict(true).open(&path) else {
+            panic!("Loaded a story with unknown fields");
+        };
+
+        assert_eq!(error.context().line, Some(3));
+
+        let builder = FetcherBuilder::new().strict(true).skip_invalid(true);
+        let fetcher = builder.open(&path).unwrap();
+
+        assert_eq!(fetcher.len(), 1);
+        assert_eq!(fetcher.diagnostics()[0].0, 3);
+    }
+}
@@ -0,0 +1,177 @@
+//! Memory estimates.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::mem::size_of_val;
+use std::sync::Arc;
+
+use super::story::Author;
+use super::story::Avatar;
+use super::story::Chapter;
+use super::story::CoverImage;
+use super::story::Story;
+use super::story::Tag;
+use super::story::TagKind;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryUsage {
+    pub index: usize,
+    pub authors: usize,
+    pub tags: usize,
+    pub caches: usize,
+}
+
+pub(crate) trait Heap {
+    fn heap(&self) -> usize;
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.index + self.authors + self.tags + self.caches
+    }
+}
+
+impl Heap for Box<str> {
+    fn heap(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "compact")]
+impl Heap for Arc<str> {
+    fn heap(&self) -> usize {
+        let size = 2 * size_of::<usize>() + self.len();
+
+        size / Arc::strong_count(self)
+    }
+}
+
+impl<T: Heap> Heap for Option<T> {
+    fn heap(&self) -> usize {
+        self.as_ref().map(Heap::heap).unwrap_or_default()
+    }
+}
+
+impl<T: Heap> Heap for Box<[T]> {
+    fn heap(&self) -> usize {
+        let items = self.iter().map(Heap::heap).sum::<usize>();
+
+        self.len() * size_of::<T>() + items
+    }
+}
+
+impl<K, V: Heap> Heap for HashMap<K, V> {
+    fn heap(&self) -> usize {
+        let values = self.values().map(Heap::heap).sum::<usize>();
+
+        self.capacity() * size_of::<(K, V)>() + values
+    }
+}
+
+impl Heap for Box<[usize]> {
+    fn heap(&self) -> usize {
+        self.len() * size_of::<usize>()
+    }
+}
+
+impl<T> Heap for Arc<T> {
+    fn heap(&self) -> usize {
+        0
+    }
+}
+
+impl Heap for Chapter {
+    fn heap(&self) -> usize {
+        self.title.heap() + self.url.heap()
+    }
+}
+
+impl Heap for CoverImage {
+    fn heap(&self) -> usize {
+        let urls = [&self.full, &self.large, &self.medium, &self.thumbnail];
+
+        urls.into_iter().map(Heap::heap).sum()
+    }
+}
+
+impl Heap for Avatar {
+    fn heap(&self) -> usize {
+        let urls = [
+            &self.x16, &self.x32, &self.x48, &self.x64, &self.x96, &self.x128,
+            &self.x160, &self.x192, &self.x256, &self.x320, &self.x384,
+            &self.x512,
+        ];
+
+        urls.into_iter().map(Heap::heap).sum()
+    }
+}
+
+impl Heap for Author {
+    fn heap(&self) -> usize {
+        let avatar = match &self.avatar {
+            Some(avatar) => avatar.heap(),
+            None => 0,
+        };
+
+        avatar + self.bio_html.heap() + self.name.heap() + self.url.heap()
+    }
+}
+
+impl Heap for Tag {
+    fn heap(&self) -> usize {
+        let kind = match &self.r#type {
+            TagKind::Unknown(name) => name.heap(),
+            _ => 0,
+        };
+
+        kind + self.name.heap() + self.old_id.heap() + self.url.heap()
+    }
+}
+
+impl Heap for Story {
+    fn heap(&self) -> usize {
+        let cover = match &self.cover_image {
+            Some(cover) => cover.heap(),
+            None => 0,
+        };
+
+        self.archive.path.heap()
+            + self.chapters.heap()
+            + cover
+            + self.description_html.heap()
+            + self.short_description.heap()
+            + self.tags.heap()
+            + self.title.heap()
+            + self.url.heap()
+    }
+}
+
+fn shared<T: Heap>(values: impl Iterator<Item = Arc<T>>) -> usize {
+    let mut seen = HashSet::new();
+    let mut total = 0;
+
+    for value in values {
+        if seen.insert(Arc::as_ptr(&value)) {
+            total += 2 * size_of::<usize>() + size_of::<T>() + value.heap();
+        }
+    }
+
+    total
+}
+
+pub(crate) fn usage(stories: &[Story], caches: usize) -> MemoryUsage {
+    let index =
+        size_of_val(stories) + stories.iter().map(Heap::heap).sum::<usize>();
+
+    let authors = shared(stories.iter().map(|story| story.author.clone()));
+
+    let tags = stories.iter().flat_map(|story| story.tags.iter().cloned());
+
+    MemoryUsage {
+        index,
+        authors,
+        tags: shared(tags),
+        caches,
+    }
+}
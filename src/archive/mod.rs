@@ -1,9 +1,57 @@
 //! Archive module.
 
+mod builder;
+mod cache;
+mod cancel;
+mod cover;
+mod diff;
+mod export;
 mod fetcher;
+mod handle;
+mod identity;
+mod indices;
 mod interner;
+mod lenient;
+mod memory;
 mod parser;
+mod plan;
+mod random;
+mod registry;
+mod remote;
+mod schema;
+mod sets;
+mod storage;
 mod story;
+mod stream;
+mod summary;
+mod text;
+mod validate;
 
+pub use builder::FetcherBuilder;
+pub use cancel::CancelToken;
+pub use cover::Cover;
+pub use diff::*;
+pub use export::Layout;
 pub use fetcher::*;
+pub use identity::ArchiveIdentity;
+pub use indices::Order;
+pub use indices::SortKey;
+pub use interner::InternerStats;
+pub use memory::MemoryUsage;
+pub use parser::parse_iter;
+pub use parser::Duplicates;
+pub use parser::StoryIter;
+pub use plan::Lookup;
+pub use plan::QueryPlan;
+pub use registry::AuthorInfo;
+pub use registry::TagInfo;
+pub use remote::*;
+pub use schema::Schema;
+pub use sets::difference;
+pub use sets::intersection;
+pub use sets::union;
 pub use story::*;
+pub use summary::StorySummary;
+pub use text::Text;
+pub use validate::Issue;
+pub use validate::ValidationReport;
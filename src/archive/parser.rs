@@ -1,101 +1,399 @@
 //! Index parser.
 
 use std::io::BufRead;
+use std::io::Result as IoResult;
+use std::mem::replace;
+use std::mem::swap;
+#[cfg(feature = "raw-index")]
+use std::str::from_utf8;
 use std::sync::mpsc::channel;
+use std::sync::mpsc::sync_channel;
 use std::sync::mpsc::Receiver;
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+use std::sync::OnceLock;
 use std::thread::spawn;
 
 use rayon::prelude::*;
 use serde::de::Error;
 use serde_json::error::Result;
+#[cfg(feature = "raw-index")]
+use serde_json::from_slice;
+#[cfg(not(feature = "raw-index"))]
 use serde_json::from_str;
 
+use super::builder::Options;
+use super::cancel::CancelToken;
+use super::lenient;
+use super::schema::LegacyStory;
+use super::schema::Schema;
 use super::story::Story;
+use super::story::StoryId;
+use crate::error::Error as CrateError;
+use crate::error::ErrorBuilder;
+use crate::error::ErrorKind;
+use crate::error::Result as CrateResult;
+
+const CHUNK: usize = 256;
 
 const TRIM: &[char] = &['"', ',', ' ', '\t', '\n', '\r'];
 
-pub fn parse(reader: impl BufRead) -> Result<Vec<Story>> {
+#[cfg(not(feature = "raw-index"))]
+type Line = String;
+
+#[cfg(feature = "raw-index")]
+type Line = Vec<u8>;
+
+pub(crate) type Parsed = (Vec<Story>, Vec<(usize, CrateError)>);
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Duplicates {
+    #[default]
+    Error,
+    KeepFirst,
+    KeepLast,
+}
+
+fn custom(message: &str) -> CrateError {
+    CrateError::index(Error::custom(message))
+}
+
+pub fn parse(reader: impl BufRead, options: &Options) -> CrateResult<Parsed> {
     let mut wrappers = String::with_capacity(2);
+    let mut chunk = Vec::with_capacity(CHUNK);
 
-    let (tx, rx) = channel();
-    let rx = spawn_parser(rx);
+    let threads = match &options.pool {
+        Some(pool) => pool.current_num_threads(),
+        None => rayon::current_num_threads(),
+    };
+
+    let schema = Arc::new(OnceLock::new());
+
+    if let Some(value) = options.schema {
+        let _ = schema.set(value);
+    }
+
+    let (tx, rx) = sync_channel(threads * 2);
+    let rx = spawn_parser(rx, options, schema.clone());
+
+    for (position, line) in lines(reader).enumerate() {
+        if options.cancelled() {
+            return Err(custom("Loading was cancelled"));
+        }
 
-    for line in reader.lines() {
         let Ok(line) = line else {
-            return Err(Error::custom("Could not read line"));
+            return Err(custom("Could not read line").with_line(position + 1));
         };
 
-        if line.len() == 1 {
-            wrappers.push_str(&line);
+        if let [wrapper] = AsRef::<[u8]>::as_ref(&line) {
+            wrappers.push(*wrapper as char);
             continue;
         }
 
-        if tx.send(line).is_ok() {
+        if schema.get().is_none() {
+            let _ = schema.set(detect(&line));
+        }
+
+        chunk.push((position, line));
+
+        if chunk.len() < CHUNK {
             continue;
         }
 
-        return Err(match rx.recv() {
-            Err(_) => Error::custom("Parser disappeared unexpectedly"),
-            Ok(Ok(_)) => Error::custom("Parser returned unexpectedly"),
-            Ok(Err(error)) => error,
-        });
+        dispatch(&tx, &rx, &mut chunk)?;
+    }
+
+    if !chunk.is_empty() {
+        dispatch(&tx, &rx, &mut chunk)?;
     }
 
     drop(tx);
 
     if wrappers != "{}" {
-        return Err(Error::custom("Invalid file structure"));
+        return Err(custom("Invalid file structure"));
     }
 
     let Ok(result) = rx.recv() else {
-        return Err(Error::custom("Missing parser result"));
+        return Err(custom("Missing parser result"));
     };
 
     result
 }
 
-fn spawn_parser(stream: Receiver<String>) -> Receiver<Result<Vec<Story>>> {
+fn dispatch(
+    tx: &SyncSender<Vec<(usize, Line)>>,
+    rx: &Receiver<CrateResult<Parsed>>,
+    chunk: &mut Vec<(usize, Line)>,
+) -> CrateResult<()> {
+    let lines = replace(chunk, Vec::with_capacity(CHUNK));
+
+    if tx.send(lines).is_ok() {
+        return Ok(());
+    }
+
+    Err(match rx.recv() {
+        Err(_) => custom("Parser disappeared unexpectedly"),
+        Ok(Ok(_)) => custom("Parser returned unexpectedly"),
+        Ok(Err(error)) => error,
+    })
+}
+
+pub struct StoryIter<I> {
+    lines: I,
+    wrappers: String,
+    schema: Option<Schema>,
+    finished: bool,
+}
+
+pub fn parse_iter(
+    reader: impl BufRead,
+) -> StoryIter<impl Iterator<Item = IoResult<Line>>> {
+    StoryIter {
+        lines: lines(reader),
+        wrappers: String::with_capacity(2),
+        schema: None,
+        finished: false,
+    }
+}
+
+impl<I> Iterator for StoryIter<I>
+where
+    I: Iterator<Item = IoResult<Line>>,
+{
+    type Item = CrateResult<Story>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        for line in &mut self.lines {
+            let Ok(line) = line else {
+                self.finished = true;
+                let error = Error::custom("Could not read line");
+                return Some(Err(CrateError::index(error)));
+            };
+
+            if let [wrapper] = AsRef::<[u8]>::as_ref(&line) {
+                self.wrappers.push(*wrapper as char);
+                continue;
+            }
+
+            let schema = *self.schema.get_or_insert_with(|| detect(&line));
+            let story = deserialize(line, true, schema);
+
+            return Some(story.map_err(CrateError::index));
+        }
+
+        self.finished = true;
+
+        match self.wrappers == "{}" {
+            true => None,
+            false => {
+                let error = Error::custom("Invalid file structure");
+                Some(Err(CrateError::index(error)))
+            }
+        }
+    }
+}
+
+fn spawn_parser(
+    stream: Receiver<Vec<(usize, Line)>>,
+    options: &Options,
+    schema: Arc<OnceLock<Schema>>,
+) -> Receiver<CrateResult<Parsed>> {
+    let pool = options.pool.clone();
+    let predicate = options.predicate.clone();
+    let cancel = options.cancel.clone();
+    let strict = options.strict;
+    let skip = options.skip_invalid;
+    let duplicates = options.duplicates;
+
     let (tx, rx) = channel();
 
     spawn(move || {
         let bridge = stream.into_iter().par_bridge();
-        let result = bridge.map(deserialize).collect();
 
-        let mut stories: Vec<Story> = match result {
+        let collect = || {
+            bridge
+                .flat_map_iter(|chunk| chunk.into_iter())
+                .map(|(position, line)| {
+                    if cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                        return Err(custom("Loading was cancelled"));
+                    }
+
+                    let schema = schema.get().copied().unwrap_or_default();
+                    let result = deserialize(line, strict, schema);
+                    let result = result.map_err(|e| {
+                        ErrorBuilder::new(ErrorKind::InvalidStory)
+                            .message(&e)
+                            .line(position + 1)
+                            .source(e)
+                            .build()
+                    });
+
+                    match result {
+                        Ok(story) => Ok((position, Ok(story))),
+                        Err(e) if skip => Ok((position, Err(e))),
+                        Err(e) => Err(e),
+                    }
+                })
+                .filter(|result| match (result, &predicate) {
+                    (Ok((_, Ok(story))), Some(predicate)) => predicate(story),
+                    _ => true,
+                })
+                .collect::<CrateResult<Vec<_>>>()
+        };
+
+        let result = match pool {
+            Some(pool) => pool.install(collect),
+            None => collect(),
+        };
+
+        let parsed = match result {
             Err(e) => return tx.send(Err(e)),
-            Ok(stories) => stories,
+            Ok(parsed) => parsed,
         };
 
-        let count = stories.len();
+        let mut stories = Vec::with_capacity(parsed.len());
+        let mut errors = Vec::new();
 
-        stories.sort_by_key(|story| story.id);
-        stories.dedup_by_key(|story| story.id);
-        stories.shrink_to_fit();
+        for (position, result) in parsed {
+            match result {
+                Ok(story) => stories.push((position, story)),
+                Err(error) => errors.push((position + 1, error)),
+            }
+        }
+
+        errors.sort_by_key(|(line, _)| *line);
+
+        let mut duplicate = None;
+
+        stories.sort_by_key(|(position, story)| (story.id, *position));
 
-        if count != stories.len() {
-            return tx.send(Err(Error::custom("Found duplicate story")));
+        stories.dedup_by(|(position, next), (_, prev)| {
+            if next.id != prev.id {
+                return false;
+            }
+
+            duplicate.get_or_insert((next.id, *position + 1));
+
+            if duplicates == Duplicates::KeepLast {
+                swap(next, prev);
+            }
+
+            true
+        });
+
+        if let (Some((id, line)), Duplicates::Error) = (duplicate, duplicates)
+        {
+            let error = custom("Found duplicate story");
+            return tx.send(Err(error.with_story(id).with_line(line)));
         }
 
-        tx.send(Ok(stories))
+        let mut stories: Vec<Story> =
+            stories.into_iter().map(|(_, story)| story).collect();
+
+        stories.shrink_to_fit();
+
+        tx.send(Ok((stories, errors)))
     });
 
     rx
 }
 
-fn deserialize(line: String) -> Result<Story> {
+#[cfg(not(feature = "raw-index"))]
+fn lines(reader: impl BufRead) -> impl Iterator<Item = IoResult<Line>> {
+    reader.lines()
+}
+
+#[cfg(feature = "raw-index")]
+fn lines(reader: impl BufRead) -> impl Iterator<Item = IoResult<Line>> {
+    reader.split(b'\n').map(|line| {
+        let mut line = line?;
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        Ok(line)
+    })
+}
+
+#[cfg(not(feature = "raw-index"))]
+fn split(line: &str) -> Result<(&str, &str)> {
     let split = line
         .splitn(2, ':')
         .map(|value| value.trim_matches(TRIM))
         .collect::<Vec<&str>>();
 
-    let (skey, json) = match split[..] {
+    match split[..] {
         [skey, json] => Ok((skey, json)),
         _ => Err(Error::custom("Invalid line format")),
-    }?;
+    }
+}
+
+#[cfg(feature = "raw-index")]
+fn trim(mut value: &[u8]) -> &[u8] {
+    let trimmed = |byte: &u8| TRIM.contains(&(*byte as char));
+
+    while let [first, rest @ ..] = value {
+        match trimmed(first) {
+            true => value = rest,
+            false => break,
+        }
+    }
+
+    while let [rest @ .., last] = value {
+        match trimmed(last) {
+            true => value = rest,
+            false => break,
+        }
+    }
+
+    value
+}
 
-    let story: Story = from_str(json)?;
+#[cfg(feature = "raw-index")]
+fn split(line: &[u8]) -> Result<(&str, &[u8])> {
+    let mut split = line.splitn(2, |&byte| byte == b':').map(trim);
 
-    let Ok(key) = skey.parse::<i32>() else {
+    let (Some(skey), Some(json)) = (split.next(), split.next()) else {
+        return Err(Error::custom("Invalid line format"));
+    };
+
+    let Ok(skey) = from_utf8(skey) else {
+        return Err(Error::custom("Invalid line key"));
+    };
+
+    Ok((skey, json))
+}
+
+fn detect(line: &Line) -> Schema {
+    match split(line) {
+        Ok((_, json)) => Schema::detect(json),
+        Err(_) => Schema::Current,
+    }
+}
+
+fn deserialize(line: Line, strict: bool, schema: Schema) -> Result<Story> {
+    let (skey, json) = split(&line)?;
+
+    #[cfg(not(feature = "raw-index"))]
+    let story: Story = match (schema, strict) {
+        (Schema::Legacy, _) => from_str::<LegacyStory>(json)?.into(),
+        (Schema::Current, true) => from_str(json)?,
+        (Schema::Current, false) => lenient::from_str(json)?,
+    };
+
+    #[cfg(feature = "raw-index")]
+    let story: Story = match (schema, strict) {
+        (Schema::Legacy, _) => from_slice::<LegacyStory>(json)?.into(),
+        (Schema::Current, true) => from_slice(json)?,
+        (Schema::Current, false) => lenient::from_slice(json)?,
+    };
+
+    let Ok(key) = skey.parse::<StoryId>() else {
         return Err(Error::custom("Invalid line key"));
     };
 
@@ -105,3 +403,48 @@ fn deserialize(line: String) -> Result<Story> {
 
     Ok(story)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Duplicates;
+    use crate::archive::fetcher::tests::archive;
+    use crate::archive::fetcher::tests::line;
+    use crate::archive::FetcherBuilder;
+    use crate::archive::StoryId;
+
+    fn titled(id: i32, title: &str) -> String {
+        line(id, "").replace("Story number 1", title)
+    }
+
+    fn title(policy: Duplicates) -> Option<String> {
+        let lines = [titled(1, "one"), titled(2, "first"), titled(2, "last")];
+        let path = archive("duplicates", &lines);
+
+        let builder = FetcherBuilder::new().duplicates(policy);
+        let fetcher = builder.open(&path).ok()?;
+
+        assert_eq!(fetcher.len(), 2);
+
+        Some(fetcher.fetch(StoryId(2))?.title.to_string())
+    }
+
+    #[test]
+    fn applies_duplicate_policy() {
+        assert_eq!(title(Duplicates::KeepFirst).as_deref(), Some("first"));
+        assert_eq!(title(Duplicates::KeepLast).as_deref(), Some("last"));
+        assert_eq!(title(Duplicates::Error), None);
+    }
+
+    #[test]
+    fn reports_duplicate_lines() {
+        let lines = [titled(1, "one"), titled(2, "first"), titled(2, "last")];
+        let path = archive("duplicate-line", &lines);
+
+        let Err(error) = FetcherBuilder::new().open(&path) else {
+            panic!("Loaded duplicate stories");
+        };
+
+        assert_eq!(error.context().story, Some(StoryId(2)));
+        assert_eq!(error.context().line, Some(4));
+    }
+}
@@ -0,0 +1,36 @@
+//! Query plans.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use super::indices::Order;
+use super::indices::SortKey;
+use super::story::StoryId;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Lookup {
+    Story(StoryId),
+    Author(i32),
+    Tag(Box<str>),
+    Sequels(StoryId),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct QueryPlan {
+    pub lookup: Option<Lookup>,
+    pub sort: Option<(SortKey, Order)>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl Display for Lookup {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Lookup::Story(id) => write!(f, "story {}", id),
+            Lookup::Author(id) => write!(f, "author {}", id),
+            Lookup::Tag(name) => write!(f, "tag {}", name),
+            Lookup::Sequels(id) => write!(f, "sequels of {}", id),
+        }
+    }
+}
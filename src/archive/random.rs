@@ -0,0 +1,61 @@
+//! Random sampling.
+
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn from_time() -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        let nanos = now.map(|d| d.as_nanos() as u64).unwrap_or_default();
+
+        Self::new(nanos)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        ((self.next() as u128 * bound as u128) >> 64) as usize
+    }
+
+    pub fn sample<T>(
+        &mut self,
+        items: impl Iterator<Item = T>,
+        count: usize,
+    ) -> Vec<T> {
+        let mut reservoir = Vec::with_capacity(count);
+
+        for (seen, item) in items.enumerate() {
+            if seen < count {
+                reservoir.push(item);
+                continue;
+            }
+
+            let slot = self.below(seen + 1);
+
+            if slot < count {
+                reservoir[slot] = item;
+            }
+        }
+
+        for i in (1..reservoir.len()).rev() {
+            let j = self.below(i + 1);
+            reservoir.swap(i, j);
+        }
+
+        reservoir
+    }
+}
@@ -0,0 +1,58 @@
+//! Tag and author registry.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::story::Author;
+use super::story::Story;
+use super::story::Tag;
+
+#[derive(Clone, Debug)]
+pub struct TagInfo {
+    pub tag: Arc<Tag>,
+    pub stories: usize,
+}
+
+pub fn tags(stories: &[Story]) -> Vec<TagInfo> {
+    let mut tags: BTreeMap<i32, TagInfo> = BTreeMap::new();
+
+    for tag in stories.iter().flat_map(|story| story.tags.iter()) {
+        let info = tags.entry(tag.id).or_insert_with(|| TagInfo {
+            tag: tag.clone(),
+            stories: 0,
+        });
+
+        info.stories += 1;
+    }
+
+    tags.into_values().collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorInfo {
+    pub author: Arc<Author>,
+    pub stories: usize,
+    pub words: u64,
+    pub likes: u64,
+}
+
+pub fn authors(stories: &[Story]) -> Vec<AuthorInfo> {
+    let mut authors: BTreeMap<i32, AuthorInfo> = BTreeMap::new();
+
+    for story in stories {
+        let author = &story.author;
+
+        let info = authors.entry(author.id).or_insert_with(|| AuthorInfo {
+            author: author.clone(),
+            stories: 0,
+            words: 0,
+            likes: 0,
+        });
+
+        info.stories += 1;
+        info.words += story.num_words.max(0) as u64;
+        info.likes += story.num_likes.max(0) as u64;
+    }
+
+    authors.into_values().collect()
+}
@@ -0,0 +1,654 @@
+//! Remote archive reader.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const BLOCK_SIZE: u64 = 1 << 20;
+const CACHE_BLOCKS: usize = 64;
+const MAX_REDIRECTS: usize = 5;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub trait Transport: Send + Sync {
+    fn length(&self) -> IoResult<u64>;
+    fn fetch(&self, start: u64, end: u64) -> IoResult<Vec<u8>>;
+}
+
+#[derive(Clone)]
+struct Location {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+pub struct Http {
+    location: Mutex<Location>,
+    timeout: Duration,
+}
+
+struct Response {
+    status: u16,
+    headers: HashMap<String, String>,
+    reader: BufReader<TcpStream>,
+}
+
+#[derive(Default)]
+struct Cache {
+    blocks: HashMap<u64, Arc<[u8]>>,
+    order: VecDeque<u64>,
+    pinned: HashSet<u64>,
+    pinning: bool,
+}
+
+#[derive(Clone)]
+pub struct Remote {
+    transport: Arc<dyn Transport>,
+    cache: Arc<Mutex<Cache>>,
+    length: u64,
+    offset: u64,
+}
+
+fn invalid(message: &str) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, message)
+}
+
+impl Location {
+    fn parse(url: &str) -> IoResult<Self> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            let kind = IoErrorKind::InvalidInput;
+            return Err(IoError::new(kind, "Only plain HTTP is supported"));
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => return Err(invalid("Invalid port in URL")),
+            },
+            None => (authority, 80),
+        };
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    fn host(&self) -> String {
+        match self.port {
+            80 => self.host.clone(),
+            port => format!("{}:{port}", self.host),
+        }
+    }
+
+    fn redirect(&self, target: &str) -> IoResult<Self> {
+        match target.starts_with('/') {
+            true => Ok(Self {
+                path: target.to_string(),
+                ..self.clone()
+            }),
+            false => Self::parse(target),
+        }
+    }
+}
+
+impl Http {
+    /// Only plain `http://` URLs are supported, there is no TLS.
+    pub fn new(url: &str) -> IoResult<Self> {
+        Ok(Self {
+            location: Mutex::new(Location::parse(url)?),
+            timeout: TIMEOUT,
+        })
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn location(&self) -> IoResult<Location> {
+        match self.location.lock() {
+            Ok(location) => Ok(location.clone()),
+            Err(_) => Err(IoError::other("Could not acquire location lock")),
+        }
+    }
+
+    fn connect(&self, location: &Location) -> IoResult<TcpStream> {
+        let address = (location.host.as_str(), location.port);
+        let mut error = invalid("Could not resolve host");
+
+        for address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                }
+                Err(e) => error = e,
+            }
+        }
+
+        Err(error)
+    }
+
+    fn request(&self, method: &str, range: &str) -> IoResult<Response> {
+        let mut location = self.location()?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self.send(&location, method, range)?;
+
+            if !matches!(response.status, 301 | 302 | 303 | 307 | 308) {
+                return Ok(response);
+            }
+
+            let Some(target) = response.headers.get("location") else {
+                return Err(invalid("Missing redirect location"));
+            };
+
+            location = location.redirect(target)?;
+
+            if matches!(response.status, 301 | 308) {
+                if let Ok(mut stored) = self.location.lock() {
+                    *stored = location.clone();
+                }
+            }
+        }
+
+        Err(invalid("Too many redirects"))
+    }
+
+    fn send(
+        &self,
+        location: &Location,
+        method: &str,
+        range: &str,
+    ) -> IoResult<Response> {
+        let mut stream = self.connect(location)?;
+
+        write!(
+            stream,
+            "{method} {} HTTP/1.1\r\nHost: {}\r\n{range}\
+             Connection: close\r\n\r\n",
+            location.path,
+            location.host(),
+        )?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+
+        reader.read_line(&mut line)?;
+
+        let status = match line.split_whitespace().nth(1) {
+            Some(code) => code.parse().map_err(|_| invalid("Bad status"))?,
+            None => return Err(invalid("Missing status line")),
+        };
+
+        let mut headers = HashMap::new();
+
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+
+            let Some((key, value)) = line.split_once(':') else {
+                break;
+            };
+
+            let key = key.trim().to_ascii_lowercase();
+            headers.insert(key, value.trim().to_string());
+        }
+
+        Ok(Response {
+            status,
+            headers,
+            reader,
+        })
+    }
+}
+
+impl Response {
+    fn length(&self) -> IoResult<Option<u64>> {
+        match self.headers.get("content-length") {
+            Some(value) => match value.parse() {
+                Ok(length) => Ok(Some(length)),
+                Err(_) => Err(invalid("Bad length")),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn chunked(&self) -> bool {
+        self.headers
+            .get("transfer-encoding")
+            .is_some_and(|value| value.contains("chunked"))
+    }
+
+    fn body(mut self, limit: u64) -> IoResult<Vec<u8>> {
+        if self.chunked() {
+            return dechunk(&mut self.reader, limit);
+        }
+
+        if self.length()?.is_some_and(|length| length > limit) {
+            return Err(invalid("Response body is too large"));
+        }
+
+        let mut body = Vec::new();
+        self.reader.take(limit + 1).read_to_end(&mut body)?;
+
+        if body.len() as u64 > limit {
+            return Err(invalid("Response body is too large"));
+        }
+
+        Ok(body)
+    }
+}
+
+fn dechunk(reader: &mut impl BufRead, limit: u64) -> IoResult<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+
+        let size = line.split(';').next().unwrap_or("").trim();
+        let Ok(size) = u64::from_str_radix(size, 16) else {
+            return Err(invalid("Invalid chunk size"));
+        };
+
+        if size == 0 {
+            return Ok(body);
+        }
+
+        let start = body.len();
+
+        if start as u64 + size > limit {
+            return Err(invalid("Response body is too large"));
+        }
+
+        body.resize(start + size as usize, 0);
+        reader.read_exact(&mut body[start..])?;
+
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+impl Transport for Http {
+    fn length(&self) -> IoResult<u64> {
+        let response = self.request("HEAD", "")?;
+
+        if response.status != 200 {
+            return Err(invalid("Unexpected response status"));
+        }
+
+        match response.length()? {
+            Some(length) => Ok(length),
+            None => Err(invalid("Missing content length")),
+        }
+    }
+
+    fn fetch(&self, start: u64, end: u64) -> IoResult<Vec<u8>> {
+        let range = format!("Range: bytes={}-{}\r\n", start, end - 1);
+        let response = self.request("GET", &range)?;
+
+        if response.status != 206 {
+            return Err(invalid("Server does not support range requests"));
+        }
+
+        let expected = end - start;
+
+        if response.length()?.is_some_and(|length| length != expected) {
+            return Err(invalid("Unexpected range length"));
+        }
+
+        let body = response.body(expected)?;
+
+        if body.len() as u64 != expected {
+            return Err(invalid("Unexpected range length"));
+        }
+
+        Ok(body)
+    }
+}
+
+impl Remote {
+    pub fn new(transport: impl Transport + 'static) -> IoResult<Self> {
+        let length = transport.length()?;
+
+        Ok(Self {
+            transport: Arc::new(transport),
+            cache: Default::default(),
+            length,
+            offset: 0,
+        })
+    }
+
+    /// Only plain `http://` URLs are supported, there is no TLS.
+    pub fn http(url: &str) -> IoResult<Self> {
+        Self::new(Http::new(url)?)
+    }
+
+    pub fn pin(&self, start: u64, end: u64) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache
+                .pinned
+                .extend(start / BLOCK_SIZE..end.div_ceil(BLOCK_SIZE));
+        }
+    }
+
+    pub fn pin_reads(&self, pinning: bool) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.pinning = pinning;
+        }
+    }
+
+    fn block(&self, index: u64) -> IoResult<Arc<[u8]>> {
+        let error = || IoError::other("Could not acquire cache lock");
+
+        let cache = self.cache.lock().map_err(|_| error())?;
+
+        if let Some(block) = cache.get(index) {
+            return Ok(block);
+        }
+
+        drop(cache);
+
+        let start = index * BLOCK_SIZE;
+        let end = (start + BLOCK_SIZE).min(self.length);
+        let block: Arc<[u8]> = self.transport.fetch(start, end)?.into();
+
+        let mut cache = self.cache.lock().map_err(|_| error())?;
+        cache.insert(index, block.clone());
+
+        Ok(block)
+    }
+}
+
+impl Cache {
+    fn get(&self, index: u64) -> Option<Arc<[u8]>> {
+        self.blocks.get(&index).cloned()
+    }
+
+    fn insert(&mut self, index: u64, block: Arc<[u8]>) {
+        if self.pinning {
+            self.pinned.insert(index);
+        }
+
+        if self.blocks.insert(index, block).is_some() {
+            return;
+        }
+
+        if self.pinned.contains(&index) {
+            return;
+        }
+
+        self.order.push_back(index);
+
+        while self.order.len() > CACHE_BLOCKS {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+
+            if !self.pinned.contains(&oldest) {
+                self.blocks.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl Read for Remote {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.offset >= self.length || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = self.offset / BLOCK_SIZE;
+        let block = self.block(index)?;
+
+        let start = (self.offset - index * BLOCK_SIZE) as usize;
+        let count = buf.len().min(block.len() - start);
+
+        buf[..count].copy_from_slice(&block[start..start + count]);
+        self.offset += count as u64;
+
+        Ok(count)
+    }
+}
+
+impl Seek for Remote {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.length.checked_add_signed(delta),
+        };
+
+        let Some(offset) = offset else {
+            let kind = IoErrorKind::InvalidInput;
+            return Err(IoError::new(kind, "Invalid seek position"));
+        };
+
+        self.offset = offset;
+
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::thread::JoinHandle;
+
+    struct Mock {
+        length: u64,
+        fetches: Arc<AtomicUsize>,
+    }
+
+    impl Transport for Mock {
+        fn length(&self) -> IoResult<u64> {
+            Ok(self.length)
+        }
+
+        fn fetch(&self, start: u64, end: u64) -> IoResult<Vec<u8>> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok((start..end).map(|i| (i % 251) as u8).collect())
+        }
+    }
+
+    fn mock(length: u64) -> (Remote, Arc<AtomicUsize>) {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let transport = Mock {
+            length,
+            fetches: fetches.clone(),
+        };
+
+        (Remote::new(transport).unwrap(), fetches)
+    }
+
+    fn serve(responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url =
+            format!("http://{}/archive.zip", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+
+                while !request.ends_with("\r\n\r\n") {
+                    if reader.read_line(&mut request).unwrap() == 0 {
+                        break;
+                    }
+                }
+
+                let mut stream = reader.into_inner();
+                let _ = stream.write_all(response.as_bytes());
+                requests.push(request);
+            }
+
+            requests
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn reads_across_blocks() {
+        let length = BLOCK_SIZE * 2 + 10;
+        let (mut remote, fetches) = mock(length);
+        let mut buf = vec![0; 20];
+
+        remote.seek(SeekFrom::Start(BLOCK_SIZE - 10)).unwrap();
+        remote.read_exact(&mut buf).unwrap();
+
+        let expected: Vec<u8> = (BLOCK_SIZE - 10..BLOCK_SIZE + 10)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        assert_eq!(buf, expected);
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        remote.seek(SeekFrom::End(-5)).unwrap();
+        let mut rest = Vec::new();
+        remote.read_to_end(&mut rest).unwrap();
+
+        assert_eq!(rest.len(), 5);
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+
+        remote.seek(SeekFrom::Start(0)).unwrap();
+        remote.read_exact(&mut buf).unwrap();
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn keeps_pinned_blocks() {
+        let mut cache = Cache {
+            pinning: true,
+            ..Default::default()
+        };
+
+        cache.insert(0, Arc::from(&[0][..]));
+        cache.pinning = false;
+
+        for index in 1..=CACHE_BLOCKS as u64 + 1 {
+            cache.insert(index, Arc::from(&[0][..]));
+        }
+
+        assert!(cache.get(0).is_some());
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(CACHE_BLOCKS as u64 + 1).is_some());
+    }
+
+    #[test]
+    fn parses_locations() {
+        let location = Location::parse("http://example.com:8080/a").unwrap();
+
+        assert_eq!(location.host(), "example.com:8080");
+        assert_eq!(location.path, "/a");
+
+        let location = Location::parse("http://example.com").unwrap();
+
+        assert_eq!(location.host(), "example.com");
+        assert_eq!(location.path, "/");
+
+        let location = location.redirect("/b").unwrap();
+
+        assert_eq!(location.host(), "example.com");
+        assert_eq!(location.path, "/b");
+
+        assert!(Location::parse("https://example.com/").is_err());
+    }
+
+    #[test]
+    fn rejects_full_responses_before_reading() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 1000000000\r\n\r\n";
+        let (url, server) = serve(vec![response.to_string()]);
+        let http = Http::new(&url).unwrap();
+
+        assert!(http.fetch(0, 10).is_err());
+
+        let requests = server.join().unwrap();
+        let port = url.split(':').nth(2).unwrap().split('/').next().unwrap();
+
+        assert!(requests[0].contains(&format!("Host: 127.0.0.1:{port}\r\n")));
+        assert!(requests[0].contains("Range: bytes=0-9\r\n"));
+    }
+
+    #[test]
+    fn caps_range_bodies() {
+        let chunked = "HTTP/1.1 206 Partial Content\r\n\
+                       Transfer-Encoding: chunked\r\n\r\n\
+                       14\r\n01234567890123456789\r\n0\r\n\r\n";
+
+        let exact = "HTTP/1.1 206 Partial Content\r\n\
+                     Content-Length: 4\r\n\r\nabcd";
+
+        let (url, server) = serve(vec![chunked.into(), exact.into()]);
+        let http = Http::new(&url).unwrap();
+
+        assert!(http.fetch(0, 10).is_err());
+        assert_eq!(http.fetch(0, 4).unwrap(), b"abcd");
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn persists_only_permanent_redirects() {
+        let found = "HTTP/1.1 302 Found\r\nLocation: /found\r\n\r\n";
+        let moved = "HTTP/1.1 301 Moved\r\nLocation: /moved\r\n\r\n";
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\n";
+
+        let responses = vec![
+            found.to_string(),
+            ok.to_string(),
+            moved.to_string(),
+            ok.to_string(),
+            ok.to_string(),
+        ];
+
+        let (url, server) = serve(responses);
+        let http = Http::new(&url).unwrap();
+
+        assert_eq!(http.length().unwrap(), 7);
+        assert_eq!(http.location().unwrap().path, "/archive.zip");
+
+        assert_eq!(http.length().unwrap(), 7);
+        assert_eq!(http.location().unwrap().path, "/moved");
+
+        assert_eq!(http.length().unwrap(), 7);
+
+        let requests = server.join().unwrap();
+
+        assert!(requests[1].starts_with("HEAD /found "));
+        assert!(requests[2].starts_with("HEAD /archive.zip "));
+        assert!(requests[4].starts_with("HEAD /moved "));
+    }
+}
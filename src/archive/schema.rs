@@ -0,0 +1,284 @@
+//! Index schemas.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::prelude::*;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_json::error::Result;
+
+use super::story::*;
+use super::text::text;
+
+const CATEGORIES: &[&str] = &[
+    "2nd Person",
+    "Adventure",
+    "Alternate Universe",
+    "Anthro",
+    "Comedy",
+    "Crossover",
+    "Dark",
+    "Drama",
+    "Equestria Girls",
+    "Horror",
+    "Human",
+    "Mystery",
+    "Random",
+    "Romance",
+    "Sad",
+    "Sci-Fi",
+    "Slice of Life",
+    "Thriller",
+    "Tragedy",
+];
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Schema {
+    #[default]
+    Current,
+    Legacy,
+}
+
+#[derive(Deserialize)]
+struct Probe {
+    archive: Option<IgnoredAny>,
+    categories: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LegacyStory {
+    #[serde(default)]
+    author: LegacyAuthor,
+    #[serde(default)]
+    categories: BTreeMap<String, bool>,
+    #[serde(default)]
+    chapter_count: i32,
+    #[serde(default)]
+    chapters: Vec<LegacyChapter>,
+    #[serde(default)]
+    comments: i32,
+    #[serde(default)]
+    content_rating: i32,
+    #[serde(default)]
+    date_modified: Option<i64>,
+    #[serde(default)]
+    description: Option<Box<str>>,
+    #[serde(default)]
+    dislikes: i32,
+    #[serde(default)]
+    full_image: Option<Box<str>>,
+    id: i32,
+    #[serde(default)]
+    image: Option<Box<str>>,
+    #[serde(default)]
+    likes: i32,
+    path: Box<str>,
+    #[serde(default)]
+    short_description: Option<Box<str>>,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    title: Option<Box<str>>,
+    #[serde(default)]
+    total_views: i32,
+    url: Box<str>,
+    #[serde(default)]
+    views: i32,
+    #[serde(default)]
+    words: i32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct LegacyAuthor {
+    id: i32,
+    name: Box<str>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct LegacyChapter {
+    date_modified: Option<i64>,
+    id: i32,
+    link: Box<str>,
+    title: Option<Box<str>>,
+    views: i32,
+    words: i32,
+}
+
+impl Schema {
+    #[cfg(not(feature = "raw-index"))]
+    pub(crate) fn detect(json: &str) -> Self {
+        Self::probe(serde_json::from_str(json))
+    }
+
+    #[cfg(feature = "raw-index")]
+    pub(crate) fn detect(json: &[u8]) -> Self {
+        Self::probe(serde_json::from_slice(json))
+    }
+
+    fn probe(probe: Result<Probe>) -> Self {
+        match probe {
+            Ok(Probe {
+                archive: None,
+                categories: Some(_),
+            }) => Schema::Legacy,
+            _ => Schema::Current,
+        }
+    }
+}
+
+fn timestamp(value: Option<i64>) -> Option<DateTime<Utc>> {
+    value.and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+}
+
+fn category(name: &str) -> Arc<Tag> {
+    let id = CATEGORIES.iter().position(|c| *c == name);
+
+    TAGS.intern(Tag {
+        id: id.map(|i| -(i as i32) - 1).unwrap_or_default(),
+        name: name.into(),
+        old_id: "".into(),
+        r#type: TagKind::Genre,
+        url: "".into(),
+    })
+}
+
+impl From<LegacyChapter> for Chapter {
+    fn from(chapter: LegacyChapter) -> Self {
+        Chapter {
+            chapter_number: 0,
+            date_modified: timestamp(chapter.date_modified),
+            date_published: None,
+            id: chapter.id,
+            num_views: chapter.views,
+            num_words: chapter.words,
+            published: true,
+            title: text(chapter.title.unwrap_or_default()),
+            url: chapter.link,
+        }
+    }
+}
+
+impl From<LegacyStory> for Story {
+    fn from(story: LegacyStory) -> Self {
+        let author = AUTHORS.intern(Author {
+            avatar: None,
+            bio_html: None,
+            date_joined: None,
+            id: story.author.id,
+            name: story.author.name,
+            num_blog_posts: None,
+            num_followers: None,
+            num_stories: None,
+            url: "".into(),
+        });
+
+        let chapters = story.chapters.into_iter().enumerate();
+        let chapters = chapters.map(|(i, chapter)| Chapter {
+            chapter_number: i as i32 + 1,
+            ..Chapter::from(chapter)
+        });
+
+        let categories = story.categories.into_iter();
+        let tags = categories
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| category(&name));
+
+        let content_rating = match story.content_rating {
+            2 => ContentRating::Mature,
+            1 => ContentRating::Teen,
+            _ => ContentRating::Everyone,
+        };
+
+        let completion_status =
+            story.status.parse().unwrap_or(CompletionStatus::Incomplete);
+
+        let cover_image = story.image.map(|image| CoverImage {
+            full: story.full_image.unwrap_or_else(|| image.clone()),
+            large: image.clone(),
+            medium: image.clone(),
+            thumbnail: image,
+        });
+
+        let description = story.description.unwrap_or_default();
+
+        Story {
+            archive: Archive {
+                date_checked: None,
+                date_created: None,
+                date_fetched: None,
+                date_updated: None,
+                path: story.path,
+            },
+            author,
+            chapters: chapters.collect(),
+            color: None,
+            completion_status,
+            content_rating,
+            cover_image,
+            date_modified: timestamp(story.date_modified),
+            date_published: None,
+            date_updated: None,
+            description_html: text(description),
+            id: StoryId(story.id),
+            num_chapters: story.chapter_count,
+            num_comments: story.comments,
+            num_dislikes: story.dislikes,
+            num_likes: story.likes,
+            num_views: story.views,
+            num_words: story.words,
+            prequel: None,
+            published: true,
+            rating: 0,
+            short_description: text(
+                story.short_description.unwrap_or_default(),
+            ),
+            status: Status::Visible,
+            submitted: true,
+            tags: tags.collect(),
+            title: text(story.title.unwrap_or_default()),
+            total_num_views: story.total_views,
+            url: story.url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::fetcher::tests::archive;
+    use crate::archive::FetcherBuilder;
+    use crate::archive::StoryId;
+    use crate::error::ErrorKind;
+
+    const LEGACY: &str = r#""5": {"author": {"id": 3, "name": "Bob"}, "categories": {"Adventure": true, "Sad": false}, "id": 5, "path": "txt/5.txt", "status": "Complete", "title": "Legacy", "url": "https://www.fimfiction.net/story/5"}"#;
+
+    const MISSING: &str = r#""6": {"author": {"id": 3, "name": "Bob"}, "id": 6, "title": "Pathless", "url": "https://www.fimfiction.net/story/6"}"#;
+
+    #[test]
+    fn converts_legacy_lines() {
+        let path = archive("legacy", &[LEGACY.to_string()]);
+        let fetcher = FetcherBuilder::new().open(&path).unwrap();
+        let story = fetcher.fetch(StoryId(5)).unwrap();
+
+        assert_eq!(&*story.title, "Legacy");
+        assert_eq!(&*story.archive.path, "txt/5.txt");
+        assert_eq!(story.author.id, 3);
+        assert_eq!(story.tags.len(), 1);
+        assert_eq!(&*story.tags[0].name, "Adventure");
+    }
+
+    #[test]
+    fn requires_legacy_identity() {
+        let lines = [LEGACY.to_string(), MISSING.to_string()];
+        let path = archive("legacy-missing", &lines);
+
+        let Err(error) = FetcherBuilder::new().open(&path) else {
+            panic!("Loaded a legacy story without a path");
+        };
+
+        assert!(matches!(error.kind(), ErrorKind::InvalidStory));
+        assert_eq!(error.context().line, Some(3));
+    }
+}
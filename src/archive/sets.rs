@@ -0,0 +1,35 @@
+//! Result set operations.
+
+use std::collections::HashSet;
+
+use super::story::StoryId;
+
+pub fn union(a: &[StoryId], b: &[StoryId]) -> Vec<StoryId> {
+    let mut seen = HashSet::with_capacity(a.len() + b.len());
+
+    a.iter()
+        .chain(b)
+        .copied()
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+pub fn intersection(a: &[StoryId], b: &[StoryId]) -> Vec<StoryId> {
+    let other: HashSet<StoryId> = b.iter().copied().collect();
+    let mut seen = HashSet::with_capacity(a.len().min(b.len()));
+
+    a.iter()
+        .copied()
+        .filter(|id| other.contains(id) && seen.insert(*id))
+        .collect()
+}
+
+pub fn difference(a: &[StoryId], b: &[StoryId]) -> Vec<StoryId> {
+    let other: HashSet<StoryId> = b.iter().copied().collect();
+    let mut seen = HashSet::with_capacity(a.len());
+
+    a.iter()
+        .copied()
+        .filter(|id| !other.contains(id) && seen.insert(*id))
+        .collect()
+}
@@ -0,0 +1,278 @@
+//! Archive storage.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::ErrorKind as IoErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::path::PathBuf;
+
+use zip::read::ZipArchive;
+use zip::result::ZipError;
+
+use super::handle::Handle;
+use super::identity::ArchiveIdentity;
+use super::identity::Sha256;
+use super::stream::Stream;
+use crate::error::Error;
+use crate::error::Result;
+
+pub const INDEX_NAMES: &[&str] =
+    &["index.json", "index.json.gz", "index.json.zst"];
+
+pub enum Storage<T> {
+    Zip(ZipArchive<Handle<T>>, Handle<T>),
+    Directory(PathBuf),
+    Merged(Vec<Storage<T>>, HashMap<Box<str>, usize>),
+}
+
+pub enum Entry<T> {
+    Zip(Stream<Handle<T>>),
+    File(BufReader<File>, u64),
+}
+
+impl<T: Read + Seek> Storage<T> {
+    pub fn zip(handle: Handle<T>) -> Result<Self> {
+        use ZipError::*;
+
+        let result = ZipArchive::new(handle.clone());
+
+        let archive = result.map_err(|e| match e {
+            InvalidArchive(e) => Error::archive(e),
+            UnsupportedArchive(e) => Error::archive(e),
+            _ => Error::archive("Unknown ZIP-file issue"),
+        })?;
+
+        Ok(Storage::Zip(archive, handle))
+    }
+
+    pub fn directory(path: PathBuf) -> Self {
+        Storage::Directory(path)
+    }
+
+    pub fn merged(
+        storages: Vec<Self>,
+        routes: HashMap<Box<str>, usize>,
+    ) -> Self {
+        Storage::Merged(storages, routes)
+    }
+
+    pub fn index_name(&self) -> Option<&'static str> {
+        INDEX_NAMES.iter().copied().find(|name| match self {
+            Storage::Zip(archive, _) => {
+                archive.file_names().any(|n| n == *name)
+            }
+            Storage::Directory(root) => root.join(name).is_file(),
+            Storage::Merged(storages, _) => {
+                storages.iter().any(|s| s.index_name() == Some(name))
+            }
+        })
+    }
+
+    pub fn identity(&self) -> Result<ArchiveIdentity> {
+        let name = self.index_name().unwrap_or(INDEX_NAMES[0]);
+
+        match self {
+            Storage::Zip(archive, handle) => {
+                zip_identity(archive, handle, name)
+            }
+            Storage::Directory(root) => file_identity(&root.join(name)),
+            Storage::Merged(storages, _) => {
+                let mut hasher = Sha256::new();
+
+                for storage in storages.iter() {
+                    hasher.update(storage.identity()?.as_bytes());
+                }
+
+                Ok(hasher.finalize())
+            }
+        }
+    }
+
+    pub fn index_range(&self) -> Option<(u64, u64)> {
+        let Storage::Zip(archive, _) = self else {
+            return None;
+        };
+
+        let mut archive = archive.clone();
+        let file = archive.by_name(self.index_name()?).ok()?;
+        let end = file.data_start() + file.compressed_size();
+
+        Some((file.header_start(), end))
+    }
+
+    pub fn entry(&self, path: &str) -> Result<Option<Entry<T>>> {
+        match self {
+            Storage::Zip(archive, handle) => zip_entry(archive, handle, path),
+            Storage::Directory(root) => file_entry(&root.join(path)),
+            Storage::Merged(storages, routes) => {
+                merged_entry(storages, routes, path)
+            }
+        }
+    }
+}
+
+fn zip_identity<T>(
+    archive: &ZipArchive<Handle<T>>,
+    handle: &Handle<T>,
+    name: &str,
+) -> Result<ArchiveIdentity>
+where
+    T: Read + Seek,
+{
+    let mut archive = archive.clone();
+    let count = archive.len() as u64;
+
+    let (start, compressed, size, checksum) = match archive.by_name(name) {
+        Ok(file) => (
+            file.data_start(),
+            file.compressed_size(),
+            file.size(),
+            file.crc32(),
+        ),
+        Err(ZipError::FileNotFound) => {
+            return Err(Error::archive("Missing story index"))
+        }
+        Err(_) => return Err(Error::archive("Could not open story index")),
+    };
+
+    let mut reader = handle.clone();
+
+    let Ok(length) = reader.seek(SeekFrom::End(0)) else {
+        return Err(Error::archive("Could not measure archive"));
+    };
+
+    let Ok(_) = reader.seek(SeekFrom::Start(start)) else {
+        return Err(Error::archive("Could not seek to story index"));
+    };
+
+    let mut hasher = Sha256::new();
+
+    for value in [length, count, compressed, size, checksum as u64] {
+        hasher.update(&value.to_le_bytes());
+    }
+
+    let Ok(_) = hasher.consume(reader.take(compressed)) else {
+        return Err(Error::archive("Could not read story index"));
+    };
+
+    Ok(hasher.finalize())
+}
+
+fn file_identity(path: &Path) -> Result<ArchiveIdentity> {
+    let Ok(file) = File::open(path) else {
+        return Err(Error::archive("Missing story index"));
+    };
+
+    let Ok(meta) = file.metadata() else {
+        return Err(Error::archive("Could not inspect story index"));
+    };
+
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok());
+
+    let mut hasher = Sha256::new();
+    hasher.update(&meta.len().to_le_bytes());
+
+    if let Some(modified) = modified {
+        hasher.update(&modified.as_nanos().to_le_bytes());
+    }
+
+    let Ok(_) = hasher.consume(BufReader::new(file)) else {
+        return Err(Error::archive("Could not read story index"));
+    };
+
+    Ok(hasher.finalize())
+}
+
+fn merged_entry<T>(
+    storages: &[Storage<T>],
+    routes: &HashMap<Box<str>, usize>,
+    path: &str,
+) -> Result<Option<Entry<T>>>
+where
+    T: Read + Seek,
+{
+    if let Some(storage) = routes.get(path).and_then(|&i| storages.get(i)) {
+        return storage.entry(path);
+    }
+
+    for storage in storages.iter().rev() {
+        if let Some(entry) = storage.entry(path)? {
+            return Ok(Some(entry));
+        }
+    }
+
+    Ok(None)
+}
+
+fn zip_entry<T>(
+    archive: &ZipArchive<Handle<T>>,
+    handle: &Handle<T>,
+    path: &str,
+) -> Result<Option<Entry<T>>>
+where
+    T: Read + Seek,
+{
+    let mut archive = archive.clone();
+
+    let file = match archive.by_name(path) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(_) => return Err(Error::archive("Could not open archive entry")),
+    };
+
+    let mut reader = handle.clone();
+
+    let Ok(_) = reader.seek(SeekFrom::Start(file.data_start())) else {
+        return Err(Error::archive("Could not seek to archive entry"));
+    };
+
+    let stream = Stream::new(
+        reader,
+        file.compression(),
+        file.compressed_size(),
+        file.size(),
+        file.crc32(),
+    )?;
+
+    Ok(Some(Entry::Zip(stream)))
+}
+
+fn file_entry<T>(path: &Path) -> Result<Option<Entry<T>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(None),
+        Err(_) => return Err(Error::archive("Could not open archive entry")),
+    };
+
+    let Ok(meta) = file.metadata() else {
+        return Err(Error::archive("Could not inspect archive entry"));
+    };
+
+    Ok(Some(Entry::File(BufReader::new(file), meta.len())))
+}
+
+impl<T: Read + Seek> Entry<T> {
+    pub fn size(&self) -> u64 {
+        match self {
+            Entry::Zip(stream) => stream.size(),
+            Entry::File(_, size) => *size,
+        }
+    }
+}
+
+impl<T: Read + Seek> Read for Entry<T> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        match self {
+            Entry::Zip(stream) => stream.read(buf),
+            Entry::File(file, _) => file.read(buf),
+        }
+    }
+}
@@ -1,20 +1,51 @@
 //! Story meta.
 
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::num::ParseIntError;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::LazyLock;
+use std::time::Duration;
 
 use chrono::prelude::*;
 use serde::de::Error;
 use serde::Deserialize;
 use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use serde_json::json;
 use serde_json::Value;
 
+use crate::error::Error as CrateError;
+
 use super::interner::Interner;
+use super::text::text;
+use super::text::Text;
 
 pub(crate) static AUTHORS: LazyLock<Interner<Author>> = Interner::r#static();
 pub(crate) static TAGS: LazyLock<Interner<Tag>> = Interner::r#static();
 
-#[derive(Clone, Debug, Deserialize)]
+pub const WORDS_PER_MINUTE: u32 = 250;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[serde(transparent)]
+pub struct StoryId(pub i32);
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Story {
     pub archive: Archive,
@@ -29,30 +60,30 @@ pub struct Story {
     pub date_published: Option<DateTime<Utc>>,
     pub date_updated: Option<DateTime<Utc>>,
     #[serde(deserialize_with = "null_to_html")]
-    pub description_html: Box<str>,
-    pub id: i32,
+    pub description_html: Text,
+    pub id: StoryId,
     pub num_chapters: i32,
     pub num_comments: i32,
     pub num_dislikes: i32,
     pub num_likes: i32,
     pub num_views: i32,
     pub num_words: i32,
-    pub prequel: Option<i32>,
+    pub prequel: Option<StoryId>,
     pub published: bool,
     pub rating: i32,
     #[serde(deserialize_with = "null_to_text")]
-    pub short_description: Box<str>,
+    pub short_description: Text,
     pub status: Status,
     pub submitted: bool,
     #[serde(deserialize_with = "tags_as_static")]
     pub tags: Box<[Arc<Tag>]>,
     #[serde(deserialize_with = "null_to_text")]
-    pub title: Box<str>,
+    pub title: Text,
     pub total_num_views: i32,
     pub url: Box<str>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Archive {
     pub date_checked: Option<DateTime<Utc>>,
@@ -62,7 +93,7 @@ pub struct Archive {
     pub path: Box<str>,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Author {
     pub avatar: Option<Avatar>,
@@ -77,7 +108,7 @@ pub struct Author {
     pub url: Box<str>,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Avatar {
     #[serde(rename = "16")]
@@ -106,7 +137,7 @@ pub struct Avatar {
     pub x512: Option<Box<str>>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Chapter {
     pub chapter_number: i32,
@@ -117,18 +148,29 @@ pub struct Chapter {
     pub num_words: i32,
     pub published: bool,
     #[serde(deserialize_with = "null_to_text")]
-    pub title: Box<str>,
+    pub title: Text,
     pub url: Box<str>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
     pub blue: u8,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum CompletionStatus {
     Cancelled,
@@ -138,15 +180,26 @@ pub enum CompletionStatus {
     Incomplete,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum ContentRating {
     Everyone,
-    Mature,
     Teen,
+    Mature,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct CoverImage {
     pub full: Box<str>,
@@ -155,7 +208,18 @@ pub struct CoverImage {
     pub thumbnail: Box<str>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum Status {
     ApproveQueue,
@@ -164,33 +228,44 @@ pub enum Status {
     Visible,
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Tag {
     pub id: i32,
     pub name: Box<str>,
     pub old_id: Box<str>,
-    pub r#type: Box<str>,
+    pub r#type: TagKind,
     pub url: Box<str>,
 }
 
-fn null_to_html<'de, D>(d: D) -> Result<Box<str>, D::Error>
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TagKind {
+    Character,
+    Content,
+    Genre,
+    Rating,
+    Series,
+    Warning,
+    Unknown(Box<str>),
+}
+
+fn null_to_html<'de, D>(d: D) -> Result<Text, D::Error>
 where
     D: Deserializer<'de>,
 {
     match Option::deserialize(d)? {
-        Some(text) => Ok(text),
-        None => Ok(Box::from("<p></p>")),
+        Some(value) => Ok(text(value)),
+        None => Ok(text(Box::from("<p></p>"))),
     }
 }
 
-fn null_to_text<'de, D>(d: D) -> Result<Box<str>, D::Error>
+fn null_to_text<'de, D>(d: D) -> Result<Text, D::Error>
 where
     D: Deserializer<'de>,
 {
     match Option::deserialize(d)? {
-        Some(text) => Ok(text),
-        None => Ok(Box::from("")),
+        Some(value) => Ok(text(value)),
+        None => Ok(text(Box::from(""))),
     }
 }
 
@@ -229,6 +304,221 @@ where
         .collect()
 }
 
+macro_rules! labels {
+    ($($type:ident ($what:literal) {
+        $($variant:ident => $label:literal $(| $alias:literal)*),+ $(,)?
+    })+) => {$(
+        impl Display for $type {
+            fn fmt(&self, f: &mut Formatter) -> FmtResult {
+                let label = match self {
+                    $($type::$variant => $label,)+
+                };
+
+                write!(f, "{}", label)
+            }
+        }
+
+        impl FromStr for $type {
+            type Err = CrateError;
+
+            fn from_str(text: &str) -> Result<Self, Self::Err> {
+                let key = normalize(text);
+
+                $(if key == normalize($label) $(|| key == normalize($alias))* {
+                    return Ok($type::$variant);
+                })+
+
+                Err(CrateError::query(concat!("Unknown ", $what)))
+            }
+        }
+    )+};
+}
+
+labels! {
+    CompletionStatus ("completion status") {
+        Cancelled => "Cancelled",
+        Complete => "Complete" | "Finished",
+        Hiatus => "On Hiatus" | "Hiatus",
+        Incomplete => "Incomplete" | "Ongoing",
+    }
+
+    ContentRating ("content rating") {
+        Everyone => "Everyone",
+        Teen => "Teen",
+        Mature => "Mature",
+    }
+
+    Status ("status") {
+        ApproveQueue => "Approve Queue",
+        NotVisible => "Not Visible",
+        PostQueue => "Post Queue",
+        Visible => "Visible",
+    }
+}
+
+fn normalize(text: &str) -> String {
+    let text = text.trim().to_lowercase();
+    text.replace([' ', '-'], "_")
+}
+
+impl TagKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TagKind::Character => "character",
+            TagKind::Content => "content",
+            TagKind::Genre => "genre",
+            TagKind::Rating => "rating",
+            TagKind::Series => "series",
+            TagKind::Warning => "warning",
+            TagKind::Unknown(kind) => kind,
+        }
+    }
+}
+
+impl From<&str> for TagKind {
+    fn from(kind: &str) -> Self {
+        match kind {
+            "character" => TagKind::Character,
+            "content" => TagKind::Content,
+            "genre" => TagKind::Genre,
+            "rating" => TagKind::Rating,
+            "series" => TagKind::Series,
+            "warning" => TagKind::Warning,
+            kind => TagKind::Unknown(kind.into()),
+        }
+    }
+}
+
+impl Display for TagKind {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TagKind {
+    fn deserialize<D>(d: D) -> Result<TagKind, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let kind = String::deserialize(d)?;
+
+        Ok(TagKind::from(kind.as_str()))
+    }
+}
+
+impl Serialize for TagKind {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_str(self.as_str())
+    }
+}
+
+fn reading_time(words: i32, wpm: u32) -> Duration {
+    let words = words.max(0) as u64;
+    let wpm = wpm.max(1) as u64;
+
+    Duration::from_secs(words * 60 / wpm)
+}
+
+impl Story {
+    pub fn reading_time(&self, wpm: u32) -> Duration {
+        reading_time(self.num_words, wpm)
+    }
+
+    pub fn like_ratio(&self) -> Option<f64> {
+        let likes = self.num_likes.max(0) as f64;
+        let total = likes + self.num_dislikes.max(0) as f64;
+
+        match total > 0.0 {
+            true => Some(likes / total),
+            false => None,
+        }
+    }
+
+    pub fn wilson_score(&self) -> f64 {
+        const Z: f64 = 1.96;
+
+        let likes = self.num_likes.max(0) as f64;
+        let total = likes + self.num_dislikes.max(0) as f64;
+
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        let ratio = likes / total;
+        let spread = ratio * (1.0 - ratio) + Z * Z / (4.0 * total);
+        let center = ratio + Z * Z / (2.0 * total);
+
+        (center - Z * (spread / total).sqrt()) / (1.0 + Z * Z / total)
+    }
+
+    pub fn views_per_word(&self) -> Option<f64> {
+        match self.num_words > 0 {
+            true => Some(self.num_views.max(0) as f64 / self.num_words as f64),
+            false => None,
+        }
+    }
+
+    pub fn max_chapter_words(&self) -> Option<i32> {
+        self.chapters.iter().map(|c| c.num_words).max()
+    }
+
+    pub fn min_chapter_words(&self) -> Option<i32> {
+        self.chapters.iter().map(|c| c.num_words).min()
+    }
+
+    pub fn avg_chapter_words(&self) -> Option<f64> {
+        let count = self.chapters.len();
+        let total: i64 =
+            self.chapters.iter().map(|c| c.num_words as i64).sum();
+
+        match count > 0 {
+            true => Some(total as f64 / count as f64),
+            false => None,
+        }
+    }
+}
+
+impl Chapter {
+    pub fn reading_time(&self, wpm: u32) -> Duration {
+        reading_time(self.num_words, wpm)
+    }
+}
+
+impl From<i32> for StoryId {
+    fn from(id: i32) -> Self {
+        StoryId(id)
+    }
+}
+
+impl From<StoryId> for i32 {
+    fn from(id: StoryId) -> Self {
+        id.0
+    }
+}
+
+impl From<StoryId> for i64 {
+    fn from(id: StoryId) -> Self {
+        id.0 as i64
+    }
+}
+
+impl FromStr for StoryId {
+    type Err = ParseIntError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        text.trim().parse().map(StoryId)
+    }
+}
+
+impl Display for StoryId {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl<'de> Deserialize<'de> for Color {
     fn deserialize<D>(d: D) -> Result<Color, D::Error>
     where
@@ -251,3 +541,73 @@ impl<'de> Deserialize<'de> for Color {
         }
     }
 }
+
+impl Serialize for Color {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Color { red, green, blue } = *self;
+
+        let object = json!({
+            "hex": hex::encode([red, green, blue]),
+            "rgb": [red, green, blue],
+        });
+
+        object.serialize(s)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::Story;
+    use super::StoryId;
+
+    pub(crate) const STORY: &str = r#"{"archive": {"date_checked": "2020-01-01T00:00:00+00:00", "date_created": "2019-01-01T00:00:00+00:00", "date_fetched": "2020-01-01T00:00:00+00:00", "date_updated": "2020-01-01T00:00:00+00:00", "path": "epub/a/story-1.epub"}, "author": {"avatar": null, "bio_html": null, "date_joined": "2012-01-01T00:00:00+00:00", "id": "7", "name": "Alice", "num_blog_posts": 3, "num_followers": 40, "num_stories": 2, "url": "https://www.fimfiction.net/user/7/Alice"}, "chapters": [{"chapter_number": 1, "date_modified": "2019-06-01T00:00:00+00:00", "date_published": "2019-06-01T00:00:00+00:00", "id": 10, "num_views": 10, "num_words": 500, "published": true, "title": "Chapter 1", "url": "https://www.fimfiction.net/story/1/1"}], "color": {"hex": "ff8800", "rgb": [255, 136, 0]}, "completion_status": "incomplete", "content_rating": "teen", "cover_image": {"full": "f", "large": "l", "medium": "m", "thumbnail": "t"}, "date_modified": "2019-06-01T00:00:00+00:00", "date_published": "2011-02-15T12:00:00+00:00", "date_updated": "2019-06-01T00:00:00+00:00", "description_html": "<p>Desc &amp; more</p>", "id": 1, "num_chapters": 1, "num_comments": 3, "num_dislikes": 1, "num_likes": 3, "num_views": 101, "num_words": 500, "prequel": null, "published": true, "rating": 50, "short_description": "short", "status": "visible", "submitted": true, "tags": [{"id": 1, "name": "Twilight Sparkle", "old_id": "c1", "type": "character", "url": "u1"}, {"id": 2, "name": "Adventure", "old_id": "g1", "type": "genre", "url": "u2"}], "title": "Story number 1", "total_num_views": 1001, "url": "https://www.fimfiction.net/story/1/story-1"}"#;
+
+    pub(crate) fn story(id: i32) -> Story {
+        let mut story: Story = serde_json::from_str(STORY).unwrap();
+        story.id = StoryId(id);
+        story
+    }
+
+    fn round_trip(story: &Story) -> Story {
+        let json = serde_json::to_string(story).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn round_trips_stories() {
+        let story = story(1);
+
+        assert_eq!(round_trip(&story), story);
+    }
+
+    #[test]
+    fn round_trips_null_fields() {
+        let json = STORY
+            .replace(r#""<p>Desc &amp; more</p>""#, "null")
+            .replace(r#""Story number 1""#, "null")
+            .replace(r#""Chapter 1""#, "null")
+            .replace(r#""short""#, "null")
+            .replace(r#"{"hex": "ff8800", "rgb": [255, 136, 0]}"#, "null")
+            .replace(r#""prequel": null"#, r#""prequel": 9"#);
+
+        let story: Story = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(&*story.description_html, "<p></p>");
+        assert_eq!(&*story.title, "");
+        assert_eq!(&*story.chapters[0].title, "");
+        assert_eq!(&*story.short_description, "");
+        assert_eq!(story.color, None);
+        assert_eq!(story.prequel, Some(StoryId(9)));
+        assert_eq!(round_trip(&story), story);
+    }
+
+    #[test]
+    fn requires_text_fields() {
+        let json = STORY.replace(r#""title": "Story number 1", "#, "");
+
+        assert!(serde_json::from_str::<Story>(&json).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! Entry stream.
+
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Take;
+
+use crc32fast::Hasher;
+use flate2::read::DeflateDecoder;
+use zip::CompressionMethod;
+
+use crate::error::Error;
+use crate::error::Result;
+
+enum Decoder<R> {
+    Stored(Take<R>),
+    Deflated(DeflateDecoder<Take<R>>),
+}
+
+pub struct Stream<R> {
+    decoder: Decoder<R>,
+    hasher: Hasher,
+    checksum: u32,
+    size: u64,
+}
+
+impl<R: Read> Stream<R> {
+    pub fn new(
+        reader: R,
+        method: CompressionMethod,
+        compressed: u64,
+        size: u64,
+        checksum: u32,
+    ) -> Result<Self> {
+        let reader = reader.take(compressed);
+
+        let decoder = match method {
+            CompressionMethod::Stored => Decoder::Stored(reader),
+            CompressionMethod::Deflated => {
+                Decoder::Deflated(DeflateDecoder::new(reader))
+            }
+            _ => return Err(Error::archive("Unsupported compression method")),
+        };
+
+        Ok(Self {
+            decoder,
+            hasher: Hasher::new(),
+            checksum,
+            size,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<R: Read> Read for Stream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let count = match &mut self.decoder {
+            Decoder::Stored(reader) => reader.read(buf)?,
+            Decoder::Deflated(reader) => reader.read(buf)?,
+        };
+
+        self.hasher.update(&buf[..count]);
+
+        let finished = count == 0 && !buf.is_empty();
+
+        if finished && self.hasher.clone().finalize() != self.checksum {
+            let kind = IoErrorKind::InvalidData;
+            return Err(IoError::new(kind, "Invalid entry checksum"));
+        }
+
+        Ok(count)
+    }
+}
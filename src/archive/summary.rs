@@ -0,0 +1,51 @@
+//! Story summaries.
+
+use chrono::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::story::CompletionStatus;
+use super::story::ContentRating;
+use super::story::Status;
+use super::story::Story;
+use super::story::StoryId;
+use super::text::Text;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StorySummary {
+    pub id: StoryId,
+    pub title: Text,
+    pub author_id: i32,
+    pub author_name: Box<str>,
+    pub num_words: i32,
+    pub completion_status: CompletionStatus,
+    pub content_rating: ContentRating,
+    pub status: Status,
+    pub date_modified: Option<DateTime<Utc>>,
+    pub date_published: Option<DateTime<Utc>>,
+    pub date_updated: Option<DateTime<Utc>>,
+}
+
+impl From<&Story> for StorySummary {
+    fn from(story: &Story) -> Self {
+        Self {
+            id: story.id,
+            title: story.title.clone(),
+            author_id: story.author.id,
+            author_name: story.author.name.clone(),
+            num_words: story.num_words,
+            completion_status: story.completion_status,
+            content_rating: story.content_rating,
+            status: story.status,
+            date_modified: story.date_modified,
+            date_published: story.date_published,
+            date_updated: story.date_updated,
+        }
+    }
+}
+
+impl From<Story> for StorySummary {
+    fn from(story: Story) -> Self {
+        Self::from(&story)
+    }
+}
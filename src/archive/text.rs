@@ -0,0 +1,66 @@
+//! Story text storage.
+
+use std::borrow::Cow;
+#[cfg(feature = "compact")]
+use std::collections::HashSet;
+#[cfg(feature = "compact")]
+use std::sync::Arc;
+#[cfg(feature = "compact")]
+use std::sync::LazyLock;
+#[cfg(feature = "compact")]
+use std::sync::RwLock;
+
+use crate::unicode::compose;
+
+#[cfg(not(feature = "compact"))]
+pub type Text = Box<str>;
+
+#[cfg(feature = "compact")]
+pub type Text = Arc<str>;
+
+#[cfg(feature = "compact")]
+static STRINGS: LazyLock<RwLock<HashSet<Arc<str>>>> =
+    LazyLock::new(Default::default);
+
+fn composed(value: Box<str>) -> Box<str> {
+    match compose(&value) {
+        Cow::Borrowed(_) => value,
+        Cow::Owned(composed) => composed.into(),
+    }
+}
+
+#[cfg(not(feature = "compact"))]
+pub(crate) fn text(value: Box<str>) -> Text {
+    composed(value)
+}
+
+#[cfg(feature = "compact")]
+pub(crate) fn text(value: Box<str>) -> Text {
+    let value = composed(value);
+
+    if let Some(text) = STRINGS.read().unwrap().get(&*value) {
+        return text.clone();
+    }
+
+    let mut store = STRINGS.write().unwrap();
+
+    match store.get(&*value) {
+        Some(text) => text.clone(),
+        None => {
+            let text = Arc::from(value);
+            store.insert(Arc::clone(&text));
+            text
+        }
+    }
+}
+
+#[cfg(not(feature = "compact"))]
+pub(crate) fn clear() {}
+
+#[cfg(feature = "compact")]
+pub(crate) fn clear() {
+    let mut store = STRINGS.write().unwrap();
+
+    store.clear();
+    store.shrink_to_fit();
+}
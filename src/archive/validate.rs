@@ -0,0 +1,119 @@
+//! Story validation.
+
+use super::story::Story;
+use super::story::StoryId;
+use crate::epub::plain;
+use crate::epub::Epub;
+
+const TOLERANCE: f64 = 0.1;
+const SLACK: usize = 50;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    InvalidEpub(String),
+    ChapterCount {
+        expected: usize,
+        found: usize,
+    },
+    MissingChapter {
+        chapter: usize,
+        path: String,
+    },
+    WordCount {
+        chapter: usize,
+        expected: usize,
+        found: usize,
+    },
+    TitleMismatch {
+        expected: String,
+        found: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidationReport {
+    pub story: StoryId,
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+fn words(text: &str) -> usize {
+    let words = text.split_whitespace();
+    words
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+fn differs(expected: usize, found: usize) -> bool {
+    let delta = expected.abs_diff(found);
+    delta > SLACK && delta as f64 > expected as f64 * TOLERANCE
+}
+
+pub fn validate(
+    story: &Story,
+    epub: Result<Epub, String>,
+) -> ValidationReport {
+    let mut report = ValidationReport {
+        story: story.id,
+        issues: Vec::new(),
+    };
+
+    let epub = match epub {
+        Ok(epub) => epub,
+        Err(message) => {
+            report.issues.push(Issue::InvalidEpub(message));
+            return report;
+        }
+    };
+
+    let title = epub.metadata().title.as_deref().map(str::trim);
+
+    if title != Some(story.title.trim()) {
+        report.issues.push(Issue::TitleMismatch {
+            expected: story.title.to_string(),
+            found: title.map(String::from),
+        });
+    }
+
+    let chapters = epub.chapters();
+
+    if chapters.len() != story.chapters.len() {
+        report.issues.push(Issue::ChapterCount {
+            expected: story.chapters.len(),
+            found: chapters.len(),
+        });
+    }
+
+    for (i, item) in chapters.iter().enumerate() {
+        let Ok(html) = epub.read_text(item) else {
+            report.issues.push(Issue::MissingChapter {
+                chapter: i,
+                path: item.path.clone(),
+            });
+
+            continue;
+        };
+
+        let Some(meta) = story.chapters.get(i) else {
+            continue;
+        };
+
+        let expected = meta.num_words.max(0) as usize;
+        let found = words(&plain(&html));
+
+        if differs(expected, found) {
+            report.issues.push(Issue::WordCount {
+                chapter: i,
+                expected,
+                found,
+            });
+        }
+    }
+
+    report
+}
@@ -0,0 +1,154 @@
+//! Markdown conversion.
+
+use super::text::any;
+use super::text::BLOCKS;
+use super::text::HIDDEN;
+use super::xml;
+use super::xml::Token;
+
+const ESCAPE: &[char] = &['\\', '*', '_', '`', '[', ']', '<', '>'];
+const LEADING: &[char] = &['#', '-', '+', '>', '|'];
+
+#[derive(Default)]
+struct Markdown {
+    text: String,
+    open: String,
+    hidden: usize,
+    space: bool,
+}
+
+fn emphasis(name: &str) -> Option<&'static str> {
+    match xml::local(name).to_ascii_lowercase().as_str() {
+        "i" | "em" | "cite" => Some("*"),
+        "b" | "strong" => Some("**"),
+        "s" | "del" | "strike" => Some("~~"),
+        _ => None,
+    }
+}
+
+fn heading(name: &str) -> Option<usize> {
+    match xml::local(name).to_ascii_lowercase().as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some((level - b'0') as usize),
+        _ => None,
+    }
+}
+
+impl Markdown {
+    fn line_start(&self) -> bool {
+        self.text.is_empty() || self.text.ends_with('\n')
+    }
+
+    fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+                continue;
+            }
+
+            if self.space && !self.line_start() {
+                self.text.push(' ');
+            }
+
+            let line_start = self.line_start();
+
+            self.space = false;
+            self.text.push_str(&self.open);
+            self.open.clear();
+
+            if ESCAPE.contains(&c) || (line_start && LEADING.contains(&c)) {
+                self.text.push('\\');
+            }
+
+            self.text.push(c);
+        }
+    }
+
+    fn close(&mut self, marker: &str) {
+        match self.open.strip_suffix(marker) {
+            Some(open) => self.open.truncate(open.len()),
+            None => self.text.push_str(marker),
+        }
+    }
+
+    fn wrap(&mut self, lines: usize) {
+        self.space = false;
+        self.open.clear();
+
+        if self.text.is_empty() {
+            return;
+        }
+
+        let trimmed = self.text.trim_end_matches('\n').len();
+
+        for _ in self.text.len() - trimmed..lines {
+            self.text.push('\n');
+        }
+    }
+
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::Start(tag) if any(HIDDEN, tag.name) => {
+                self.hidden += !tag.closed as usize;
+            }
+            Token::End(name) if any(HIDDEN, name) => {
+                self.hidden = self.hidden.saturating_sub(1);
+            }
+            _ if self.hidden > 0 => {}
+            Token::Start(tag) if tag.is("br") => {
+                if !self.line_start() {
+                    self.text.push('\\');
+                }
+
+                self.wrap(1);
+            }
+            Token::Start(tag) if tag.is("hr") => {
+                self.wrap(2);
+                self.text.push_str("---");
+                self.wrap(2);
+            }
+            Token::Start(tag) if tag.is("li") => {
+                self.wrap(1);
+                self.open.push_str("- ");
+            }
+            Token::Start(tag) => {
+                if let Some(level) = heading(tag.name) {
+                    self.wrap(2);
+                    self.open.push_str(&"#".repeat(level));
+                    self.open.push(' ');
+                } else if let Some(marker) = emphasis(tag.name) {
+                    if self.space && !self.line_start() {
+                        self.text.push(' ');
+                        self.space = false;
+                    }
+
+                    self.open.push_str(marker);
+                } else if any(BLOCKS, tag.name) {
+                    self.wrap(2);
+                }
+            }
+            Token::End(name) => {
+                if let Some(marker) = emphasis(name) {
+                    self.close(marker);
+                } else if name.eq_ignore_ascii_case("li") {
+                    self.wrap(1);
+                } else if heading(name).is_some() || any(BLOCKS, name) {
+                    self.wrap(2);
+                }
+            }
+            Token::Text(text) => self.push(&text),
+        }
+    }
+}
+
+pub fn to_markdown(html: &str) -> String {
+    let mut markdown = Markdown::default();
+
+    for token in xml::tokens(html) {
+        markdown.token(token);
+    }
+
+    let end = markdown.text.trim_end().len();
+    markdown.text.truncate(end);
+
+    markdown.text
+}
@@ -0,0 +1,373 @@
+//! Epub module.
+
+mod markdown;
+mod sanitize;
+mod terminal;
+mod text;
+mod toc;
+mod xml;
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::io::Read;
+use std::sync::Arc;
+
+use zip::read::ZipArchive;
+use zip::result::ZipError;
+
+use crate::error::Error;
+use crate::error::ErrorBuilder;
+use crate::error::ErrorKind;
+use crate::error::Result;
+
+use self::xml::Token;
+
+pub use self::markdown::to_markdown;
+pub use self::sanitize::sanitize;
+pub use self::terminal::to_terminal;
+pub use self::text::plain;
+pub use self::text::Format;
+pub use self::toc::TocEntry;
+
+const CHAPTERS: &[&str] = &["application/xhtml+xml", "text/html"];
+
+const NCX: &str = "application/x-dtbncx+xml";
+
+type Archive = ZipArchive<Cursor<Arc<[u8]>>>;
+
+#[derive(Clone, Debug, Default)]
+pub struct Metadata {
+    pub identifier: Option<String>,
+    pub title: Option<String>,
+    pub creators: Vec<String>,
+    pub contributors: Vec<String>,
+    pub language: Option<String>,
+    pub date: Option<String>,
+    pub modified: Option<String>,
+    pub publisher: Option<String>,
+    pub description: Option<String>,
+    pub rights: Option<String>,
+    pub source: Option<String>,
+    pub subjects: Vec<String>,
+}
+
+impl Metadata {
+    fn set(&mut self, field: &str, value: &str) {
+        let value = value.to_string();
+
+        match field {
+            "identifier" => self.identifier = Some(value),
+            "title" => self.title = Some(value),
+            "creator" => self.creators.push(value),
+            "contributor" => self.contributors.push(value),
+            "language" => self.language = Some(value),
+            "date" => self.date = Some(value),
+            "dcterms:modified" => self.modified = Some(value),
+            "publisher" => self.publisher = Some(value),
+            "description" => self.description = Some(value),
+            "rights" => self.rights = Some(value),
+            "source" => self.source = Some(value),
+            "subject" => self.subjects.push(value),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub id: String,
+    pub path: String,
+    pub media_type: String,
+    pub properties: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub data: Vec<u8>,
+    pub media_type: String,
+}
+
+pub struct Epub {
+    archive: Archive,
+    metadata: Metadata,
+    manifest: Vec<Item>,
+    spine: Vec<usize>,
+    cover: Option<usize>,
+}
+
+fn invalid(message: &str) -> Error {
+    ErrorBuilder::new(ErrorKind::InvalidStory)
+        .message(message)
+        .build()
+}
+
+fn entry(archive: &Archive, path: &str) -> Result<Vec<u8>> {
+    let mut archive = archive.clone();
+
+    let mut file = match archive.by_name(path) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => {
+            return Err(invalid(&format!("Missing epub entry {path}")))
+        }
+        Err(_) => return Err(invalid("Could not open epub entry")),
+    };
+
+    let mut buf = Vec::with_capacity(file.size() as usize);
+
+    let Ok(_) = file.read_to_end(&mut buf) else {
+        return Err(invalid("Could not read epub entry"));
+    };
+
+    Ok(buf)
+}
+
+fn text(bytes: Vec<u8>) -> Result<String> {
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(_) => Err(invalid("Invalid epub text encoding")),
+    }
+}
+
+fn decode(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = match (bytes[i], href.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) => u8::from_str_radix(hex, 16).ok(),
+            _ => None,
+        };
+
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+pub fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut parts: Vec<&str> = base.split('/').collect();
+
+    parts.pop();
+
+    let decoded = decode(href);
+
+    for part in decoded.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    parts.retain(|part| !part.is_empty());
+    parts.join("/")
+}
+
+fn rootfile(archive: &Archive) -> Result<String> {
+    let container = text(entry(archive, "META-INF/container.xml")?)?;
+
+    for token in xml::tokens(&container) {
+        let Token::Start(tag) = token else {
+            continue;
+        };
+
+        if let (true, Some(path)) =
+            (tag.is("rootfile"), tag.attribute("full-path"))
+        {
+            return Ok(path.to_string());
+        }
+    }
+
+    Err(invalid("Missing epub rootfile"))
+}
+
+impl Epub {
+    pub fn parse(bytes: impl Into<Arc<[u8]>>) -> Result<Self> {
+        let Ok(archive) = ZipArchive::new(Cursor::new(bytes.into())) else {
+            return Err(invalid("Could not open epub"));
+        };
+
+        let root = rootfile(&archive)?;
+        let package = text(entry(&archive, &root)?)?;
+
+        let mut metadata = Metadata::default();
+        let mut manifest = Vec::new();
+        let mut idrefs = Vec::new();
+        let mut cover_id = None;
+        let mut field: Option<String> = None;
+        let mut value = String::new();
+
+        for token in xml::tokens(&package) {
+            match token {
+                Token::Start(tag) if tag.is("item") => {
+                    let (Some(id), Some(href)) =
+                        (tag.attribute("id"), tag.attribute("href"))
+                    else {
+                        continue;
+                    };
+
+                    let properties = tag
+                        .attribute("properties")
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(String::from)
+                        .collect();
+
+                    manifest.push(Item {
+                        id: id.to_string(),
+                        path: resolve(&root, href),
+                        media_type: tag
+                            .attribute("media-type")
+                            .unwrap_or_default()
+                            .to_string(),
+                        properties,
+                    });
+                }
+                Token::Start(tag) if tag.is("itemref") => {
+                    if let Some(idref) = tag.attribute("idref") {
+                        idrefs.push(idref.to_string());
+                    }
+                }
+                Token::Start(tag)
+                    if tag.is("meta")
+                        && tag.attribute("name") == Some("cover") =>
+                {
+                    cover_id = tag.attribute("content").map(String::from);
+                }
+                Token::Start(tag) if !tag.closed && tag.is("meta") => {
+                    field = tag.attribute("property").map(String::from);
+                    value.clear();
+                }
+                Token::Start(tag) if tag.name.starts_with("dc:") => {
+                    field = match tag.closed {
+                        true => None,
+                        false => Some(xml::local(tag.name).to_string()),
+                    };
+
+                    value.clear();
+                }
+                Token::Text(text) if field.is_some() => {
+                    value.push_str(&text);
+                }
+                Token::End(_) => {
+                    if let Some(name) = field.take() {
+                        metadata.set(&name, value.trim());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let ids: HashMap<&str, usize> = manifest
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (item.id.as_str(), i))
+            .collect();
+
+        let spine = idrefs
+            .iter()
+            .filter_map(|idref| ids.get(idref.as_str()).copied())
+            .collect();
+
+        let cover = manifest
+            .iter()
+            .position(|item| {
+                item.properties.iter().any(|p| p == "cover-image")
+            })
+            .or_else(|| cover_id.and_then(|id| ids.get(id.as_str()).copied()));
+
+        Ok(Self {
+            archive,
+            metadata,
+            manifest,
+            spine,
+            cover,
+        })
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn manifest(&self) -> &[Item] {
+        &self.manifest
+    }
+
+    pub fn spine(&self) -> Vec<&Item> {
+        self.spine.iter().map(|&i| &self.manifest[i]).collect()
+    }
+
+    pub fn chapters(&self) -> Vec<&Item> {
+        let mut chapters = self.spine();
+
+        chapters.retain(|item| {
+            CHAPTERS.contains(&item.media_type.as_str())
+                && !item.properties.iter().any(|p| p == "nav")
+        });
+
+        chapters
+    }
+
+    pub fn images(&self) -> Vec<&Item> {
+        let images = self.manifest.iter();
+
+        images
+            .filter(|item| item.media_type.starts_with("image/"))
+            .collect()
+    }
+
+    pub fn cover(&self) -> Option<&Item> {
+        self.cover.map(|i| &self.manifest[i])
+    }
+
+    pub fn toc(&self) -> Result<Vec<TocEntry>> {
+        let has = |property: &str| {
+            let mut items = self.manifest.iter();
+            items.find(|item| item.properties.iter().any(|p| p == property))
+        };
+
+        if let Some(item) = has("nav") {
+            return Ok(toc::nav(&item.path, &self.read_text(item)?));
+        }
+
+        let mut items = self.manifest.iter();
+        let ncx = items.find(|item| item.media_type == NCX);
+
+        match ncx {
+            Some(item) => Ok(toc::ncx(&item.path, &self.read_text(item)?)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn cover_image(&self) -> Result<Option<Image>> {
+        let Some(item) = self.cover() else {
+            return Ok(None);
+        };
+
+        Ok(Some(Image {
+            data: self.read(item)?,
+            media_type: item.media_type.clone(),
+        }))
+    }
+
+    pub fn read(&self, item: &Item) -> Result<Vec<u8>> {
+        entry(&self.archive, &item.path)
+    }
+
+    pub fn read_text(&self, item: &Item) -> Result<String> {
+        text(self.read(item)?)
+    }
+}
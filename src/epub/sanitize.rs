@@ -0,0 +1,342 @@
+//! Markup sanitizer.
+
+use super::text::any;
+use super::text::BLOCKS;
+use super::xml;
+use super::xml::Tag;
+use super::xml::Token;
+
+const HIDDEN: &[&str] = &[
+    "head", "script", "style", "title", "iframe", "object", "noscript",
+    "template", "svg", "math",
+];
+
+const VOID: &[&str] = &["br", "col", "hr", "img", "wbr"];
+
+const TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "address",
+    "article",
+    "aside",
+    "b",
+    "big",
+    "blockquote",
+    "br",
+    "caption",
+    "center",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "samp",
+    "section",
+    "small",
+    "span",
+    "strike",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "tt",
+    "u",
+    "ul",
+    "var",
+    "wbr",
+];
+
+const GLOBAL: &[&str] = &["class", "dir", "id", "lang", "title"];
+
+const SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+fn attributes(tag: &str) -> &'static [&'static str] {
+    match tag {
+        "a" => &["href"],
+        "img" => &["src", "alt", "width", "height"],
+        "td" | "th" => &["colspan", "rowspan"],
+        "col" | "colgroup" => &["span"],
+        "ol" => &["start", "type"],
+        "blockquote" | "q" | "del" | "ins" => &["cite"],
+        _ => &[],
+    }
+}
+
+#[derive(Default)]
+struct Sanitizer {
+    html: String,
+    open: Vec<String>,
+    hidden: usize,
+}
+
+fn escape(text: &str, html: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+}
+
+fn valid(name: &str) -> bool {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == ':' || c == '-';
+
+    !name.is_empty() && name.chars().all(valid)
+}
+
+fn name(name: &str) -> Option<String> {
+    match valid(name) {
+        true => Some(xml::local(name).to_ascii_lowercase()),
+        false => None,
+    }
+}
+
+fn url(value: &str) -> Option<String> {
+    let strip = |c: &char| !c.is_ascii_whitespace() && !c.is_control();
+    let url: String = value.chars().filter(strip).collect();
+
+    let end = url.find(['/', '?', '#']).unwrap_or(url.len());
+
+    let Some((scheme, _)) = url[..end].split_once(':') else {
+        return Some(url);
+    };
+
+    match any(SCHEMES, scheme) {
+        true => Some(url),
+        false => None,
+    }
+}
+
+fn attribute(tag: &str, key: &str, value: &str) -> Option<(String, String)> {
+    let key = name(key)?;
+
+    if !any(GLOBAL, &key) && !any(attributes(tag), &key) {
+        return None;
+    }
+
+    match key.as_str() {
+        "href" | "src" | "cite" => Some((key, url(value)?)),
+        _ => Some((key, value.to_string())),
+    }
+}
+
+impl Sanitizer {
+    fn close(&mut self, name: &str) {
+        let Some(i) = self.open.iter().rposition(|open| open == name) else {
+            return;
+        };
+
+        for name in self.open.split_off(i).iter().rev() {
+            self.html.push_str("</");
+            self.html.push_str(name);
+            self.html.push('>');
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        let Some(name) = name(tag.name) else {
+            return;
+        };
+
+        if !any(TAGS, &name) {
+            return;
+        }
+
+        if any(BLOCKS, &name) && self.open.last().is_some_and(|n| n == "p") {
+            self.close("p");
+        }
+
+        self.html.push('<');
+        self.html.push_str(&name);
+
+        for (key, value) in tag.attributes.iter() {
+            let Some((key, value)) = attribute(&name, key, value) else {
+                continue;
+            };
+
+            self.html.push(' ');
+            self.html.push_str(&key);
+            self.html.push_str("=\"");
+            escape(&value, &mut self.html);
+            self.html.push('"');
+        }
+
+        if tag.closed || any(VOID, &name) {
+            self.html.push_str("/>");
+        } else {
+            self.html.push('>');
+            self.open.push(name);
+        }
+    }
+
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::Start(tag) if any(HIDDEN, tag.name) => {
+                self.hidden += !tag.closed as usize;
+            }
+            Token::End(name) if any(HIDDEN, name) => {
+                self.hidden = self.hidden.saturating_sub(1);
+            }
+            _ if self.hidden > 0 => {}
+            Token::Start(tag) => self.start(tag),
+            Token::End(end) => {
+                if let Some(name) = name(end) {
+                    self.close(&name);
+                }
+            }
+            Token::Text(text) => escape(&text, &mut self.html),
+        }
+    }
+}
+
+pub fn sanitize(html: &str) -> String {
+    let mut sanitizer = Sanitizer::default();
+
+    for token in xml::tokens(html) {
+        sanitizer.token(token);
+    }
+
+    while let Some(name) = sanitizer.open.first().cloned() {
+        sanitizer.close(&name);
+    }
+
+    sanitizer.html.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize;
+
+    #[test]
+    fn keeps_formatting() {
+        let html = "<p>Hello <em>there</em><br>friend</p>";
+
+        assert_eq!(sanitize(html), "<p>Hello <em>there</em><br/>friend</p>");
+    }
+
+    #[test]
+    fn drops_invalid_tag_names() {
+        let html = "<img/src=x/onerror=alert(1)>";
+
+        assert_eq!(sanitize(html), "");
+    }
+
+    #[test]
+    fn drops_unknown_tags() {
+        let html = "<meta http-equiv=refresh content=0><font>text</font>";
+
+        assert_eq!(sanitize(html), "text");
+    }
+
+    #[test]
+    fn drops_hidden_content() {
+        let html = "<script>alert(1)</script><style>p {}</style>text";
+
+        assert_eq!(sanitize(html), "text");
+    }
+
+    #[test]
+    fn drops_event_handlers() {
+        let html = "<p onclick=\"alert(1)\" class=\"a\">text</p>";
+
+        assert_eq!(sanitize(html), "<p class=\"a\">text</p>");
+    }
+
+    #[test]
+    fn drops_unlisted_attributes() {
+        let html = "<p style=\"x\" href=\"/a\" data-x=\"1\">text</p>";
+
+        assert_eq!(sanitize(html), "<p>text</p>");
+    }
+
+    #[test]
+    fn drops_invalid_attribute_names() {
+        let html = "<p \"onclick=alert(1)\">text</p>";
+
+        assert_eq!(sanitize(html), "<p>text</p>");
+    }
+
+    #[test]
+    fn drops_script_urls() {
+        let urls = [
+            "javascript:alert(1)",
+            "java&#9;script:alert(1)",
+            "java&#10;script:alert(1)",
+            " JavaScript:alert(1)",
+            "java&#0;script:alert(1)",
+            "vbscript:msgbox(1)",
+            "data:text/html,<script>alert(1)</script>",
+        ];
+
+        for url in urls {
+            let html = format!("<a href=\"{url}\">link</a>");
+
+            assert_eq!(sanitize(&html), "<a>link</a>", "{url}");
+        }
+    }
+
+    #[test]
+    fn keeps_safe_urls() {
+        let urls = [
+            "http://example.com/",
+            "https://example.com/a?b#c",
+            "mailto:pony@example.com",
+            "chapter-1.html#top",
+            "/story/1",
+        ];
+
+        for url in urls {
+            let html = format!("<a href=\"{url}\">link</a>");
+            let safe = format!("<a href=\"{url}\">link</a>");
+
+            assert_eq!(sanitize(&html), safe, "{url}");
+        }
+    }
+
+    #[test]
+    fn escapes_attribute_values() {
+        let html = "<a title='\"><script>'>link</a>";
+
+        assert_eq!(
+            sanitize(html),
+            "<a title=\"&quot;&gt;&lt;script&gt;\">link</a>"
+        );
+    }
+}
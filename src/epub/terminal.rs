@@ -0,0 +1,224 @@
+//! Terminal renderer.
+
+use unicode_width::UnicodeWidthChar;
+use unicode_width::UnicodeWidthStr;
+
+use super::sanitize;
+use super::text::any;
+use super::text::BLOCKS;
+use super::xml;
+use super::xml::Token;
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Default)]
+struct Terminal {
+    text: String,
+    width: usize,
+    column: usize,
+    word: String,
+    length: usize,
+    space: bool,
+    bold: usize,
+    italic: usize,
+    underline: usize,
+    quote: usize,
+    bullet: bool,
+}
+
+impl Terminal {
+    fn prefix(&self) -> String {
+        let mut prefix = "│ ".repeat(self.quote);
+
+        if self.bullet {
+            prefix.push_str("• ");
+        }
+
+        prefix
+    }
+
+    fn style(&mut self) {
+        let mut codes = Vec::new();
+
+        if self.bold > 0 {
+            codes.push("1");
+        }
+
+        if self.italic > 0 {
+            codes.push("3");
+        }
+
+        if self.underline > 0 {
+            codes.push("4");
+        }
+
+        self.word.push_str(RESET);
+
+        if !codes.is_empty() {
+            self.word.push_str(&format!("\x1b[{}m", codes.join(";")));
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.word.is_empty() || (self.length == 0 && self.column == 0) {
+            return;
+        }
+
+        if self.length > 0 {
+            if self.column == 0 {
+                let prefix = self.prefix();
+                self.column = prefix.width();
+                self.text.push_str(&prefix);
+                self.bullet = false;
+            } else if self.column + 1 + self.length > self.width {
+                self.text.push('\n');
+                let prefix = "│ ".repeat(self.quote);
+                self.column = prefix.width();
+                self.text.push_str(&prefix);
+            } else if self.space {
+                self.text.push(' ');
+                self.column += 1;
+            }
+        }
+
+        self.column += self.length;
+        self.text.push_str(&self.word);
+        self.word.clear();
+        self.length = 0;
+        self.space = false;
+    }
+
+    fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.flush();
+                self.space = self.column > 0;
+                continue;
+            }
+
+            if c.is_control() {
+                continue;
+            }
+
+            self.word.push(c);
+            self.length += c.width().unwrap_or(0);
+        }
+    }
+
+    fn wrap(&mut self, lines: usize) {
+        self.flush();
+        self.space = false;
+
+        if self.text.is_empty() {
+            return;
+        }
+
+        self.column = 0;
+
+        let trimmed = self.text.trim_end_matches('\n').len();
+
+        for _ in self.text.len() - trimmed..lines {
+            self.text.push('\n');
+        }
+    }
+
+    fn toggle(&mut self, name: &str, open: bool) -> bool {
+        let counter = match xml::local(name).to_ascii_lowercase().as_str() {
+            "b" | "strong" => &mut self.bold,
+            "i" | "em" | "cite" => &mut self.italic,
+            "u" | "ins" => &mut self.underline,
+            _ => return false,
+        };
+
+        *counter = match open {
+            true => *counter + 1,
+            false => counter.saturating_sub(1),
+        };
+
+        self.style();
+
+        true
+    }
+
+    fn heading(&mut self, open: bool) {
+        if open {
+            self.wrap(2);
+            self.bold += 1;
+            self.style();
+        } else {
+            self.bold = self.bold.saturating_sub(1);
+            self.style();
+            self.wrap(2);
+        }
+    }
+
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::Start(tag) if tag.is("br") => self.wrap(1),
+            Token::Start(tag) if tag.is("hr") => {
+                self.wrap(2);
+                self.text.push_str(&"─".repeat(self.width));
+                self.wrap(2);
+            }
+            Token::Start(tag) if tag.is("blockquote") => {
+                self.wrap(2);
+                self.quote += 1;
+            }
+            Token::End(name) if xml::local(name) == "blockquote" => {
+                self.wrap(2);
+                self.quote = self.quote.saturating_sub(1);
+            }
+            Token::Start(tag) if tag.is("li") => {
+                self.wrap(1);
+                self.bullet = true;
+            }
+            Token::End(name) if xml::local(name) == "li" => self.wrap(1),
+            Token::Start(tag) if heading(tag.name) => self.heading(true),
+            Token::End(name) if heading(name) => self.heading(false),
+            Token::Start(tag) if self.toggle(tag.name, true) => {}
+            Token::End(name) if self.toggle(name, false) => {}
+            Token::Start(tag) if any(BLOCKS, tag.name) => self.wrap(2),
+            Token::End(name) if any(BLOCKS, name) => self.wrap(2),
+            Token::Text(text) => self.push(&text),
+            _ => {}
+        }
+    }
+}
+
+fn heading(name: &str) -> bool {
+    let name = xml::local(name).to_ascii_lowercase();
+    matches!(name.as_bytes(), [b'h', b'1'..=b'6'])
+}
+
+pub fn to_terminal(html: &str, width: usize) -> String {
+    let mut terminal = Terminal {
+        width: width.max(16),
+        ..Default::default()
+    };
+
+    for token in xml::tokens(&sanitize(html)) {
+        terminal.token(token);
+    }
+
+    terminal.flush();
+
+    let end = terminal.text.trim_end().len();
+    terminal.text.truncate(end);
+    terminal.text.push_str(RESET);
+
+    terminal.text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_terminal;
+    use super::RESET;
+
+    #[test]
+    fn drops_control_characters() {
+        let text = to_terminal("<p>hi &#27;]0;pwned&#7; &#x9b;2J</p>", 80);
+        let text = text.strip_suffix(RESET).unwrap();
+
+        assert_eq!(text, "hi ]0;pwned 2J");
+    }
+}
@@ -0,0 +1,121 @@
+//! Plain text extraction.
+
+use super::xml;
+use super::xml::Token;
+
+pub(super) const HIDDEN: &[&str] = &["head", "script", "style", "title"];
+
+pub(super) const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    #[default]
+    Html,
+    Plain,
+}
+
+#[derive(Default)]
+struct Plain {
+    text: String,
+    hidden: usize,
+    space: bool,
+}
+
+pub(super) fn any(names: &[&str], name: &str) -> bool {
+    let name = xml::local(name);
+    names.iter().any(|n| n.eq_ignore_ascii_case(name))
+}
+
+impl Plain {
+    fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+                continue;
+            }
+
+            if self.space
+                && !self.text.is_empty()
+                && !self.text.ends_with('\n')
+            {
+                self.text.push(' ');
+            }
+
+            self.space = false;
+            self.text.push(c);
+        }
+    }
+
+    fn wrap(&mut self, lines: usize) {
+        self.space = false;
+
+        if self.text.is_empty() {
+            return;
+        }
+
+        let trimmed = self.text.trim_end_matches('\n').len();
+
+        for _ in self.text.len() - trimmed..lines {
+            self.text.push('\n');
+        }
+    }
+
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::Start(tag) if any(HIDDEN, tag.name) => {
+                self.hidden += !tag.closed as usize;
+            }
+            Token::End(name) if any(HIDDEN, name) => {
+                self.hidden = self.hidden.saturating_sub(1);
+            }
+            _ if self.hidden > 0 => {}
+            Token::Start(tag) if tag.is("br") => self.wrap(1),
+            Token::Start(tag) if any(BLOCKS, tag.name) => self.wrap(2),
+            Token::End(name) if any(BLOCKS, name) => self.wrap(2),
+            Token::Text(text) => self.push(&text),
+            _ => {}
+        }
+    }
+}
+
+pub fn plain(html: &str) -> String {
+    let mut plain = Plain::default();
+
+    for token in xml::tokens(html) {
+        plain.token(token);
+    }
+
+    let end = plain.text.trim_end().len();
+    plain.text.truncate(end);
+
+    plain.text
+}
@@ -0,0 +1,91 @@
+//! Table of contents.
+
+use super::resolve;
+use super::xml;
+use super::xml::Token;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TocEntry {
+    pub title: String,
+    pub path: String,
+    pub fragment: Option<String>,
+    pub level: usize,
+}
+
+fn entry(base: &str, href: &str, title: &str, level: usize) -> TocEntry {
+    let fragment = href.split_once('#').map(|(_, f)| f.to_string());
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    TocEntry {
+        title,
+        path: resolve(base, href),
+        fragment,
+        level,
+    }
+}
+
+pub fn nav(base: &str, text: &str) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    let mut inside = false;
+    let mut level: usize = 0;
+    let mut link: Option<String> = None;
+    let mut title = String::new();
+
+    for token in xml::tokens(text) {
+        match token {
+            Token::Start(tag) if tag.is("nav") => {
+                inside = tag.attribute("type") == Some("toc");
+            }
+            Token::End(name) if inside && xml::local(name) == "nav" => break,
+            _ if !inside => {}
+            Token::Start(tag) if tag.is("ol") => level += 1,
+            Token::End(name) if xml::local(name) == "ol" => {
+                level = level.saturating_sub(1);
+            }
+            Token::Start(tag) if tag.is("a") => {
+                link = tag.attribute("href").map(String::from);
+                title.clear();
+            }
+            Token::Text(text) if link.is_some() => title.push_str(&text),
+            Token::End(name) if xml::local(name) == "a" => {
+                if let Some(href) = link.take() {
+                    let depth = level.saturating_sub(1);
+                    entries.push(entry(base, &href, &title, depth));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+pub fn ncx(base: &str, text: &str) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    let mut level: usize = 0;
+    let mut label = false;
+    let mut title = String::new();
+
+    for token in xml::tokens(text) {
+        match token {
+            Token::Start(tag) if tag.is("navPoint") => {
+                level += 1;
+                title.clear();
+            }
+            Token::End(name) if xml::local(name) == "navPoint" => {
+                level = level.saturating_sub(1);
+            }
+            Token::Start(tag) if tag.is("text") => label = true,
+            Token::End(name) if xml::local(name) == "text" => label = false,
+            Token::Text(text) if label => title.push_str(&text),
+            Token::Start(tag) if tag.is("content") && level > 0 => {
+                if let Some(src) = tag.attribute("src") {
+                    entries.push(entry(base, src, &title, level - 1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
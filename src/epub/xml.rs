@@ -0,0 +1,277 @@
+//! Markup tokenizer.
+
+use std::borrow::Cow;
+
+const RAW: &[&str] = &["script", "style"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token<'a> {
+    Start(Tag<'a>),
+    End(&'a str),
+    Text(Cow<'a, str>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag<'a> {
+    pub name: &'a str,
+    pub attributes: Vec<(&'a str, Cow<'a, str>)>,
+    pub closed: bool,
+}
+
+pub struct Tokens<'a> {
+    text: &'a str,
+    raw: Option<&'a str>,
+}
+
+pub fn tokens(text: &str) -> Tokens<'_> {
+    Tokens { text, raw: None }
+}
+
+pub fn local(name: &str) -> &str {
+    match name.rsplit_once(':') {
+        Some((_, local)) => local,
+        None => name,
+    }
+}
+
+impl<'a> Tag<'a> {
+    pub fn is(&self, name: &str) -> bool {
+        local(self.name).eq_ignore_ascii_case(name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| local(key).eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_ref())
+    }
+}
+
+impl<'a> Tokens<'a> {
+    fn advance(&mut self, count: usize) -> &'a str {
+        let (head, tail) = self.text.split_at(count);
+        self.text = tail;
+        head
+    }
+
+    fn skip(&mut self, terminator: &str) {
+        let count = match self.text.find(terminator) {
+            Some(i) => i + terminator.len(),
+            None => self.text.len(),
+        };
+
+        self.advance(count);
+    }
+
+    fn raw(&mut self, name: &'a str) -> Option<Token<'a>> {
+        let lower = self.text.to_ascii_lowercase();
+        let close = format!("</{}", name.to_ascii_lowercase());
+
+        let count = lower.find(&close).unwrap_or(self.text.len());
+        let text = self.advance(count);
+
+        match text.is_empty() {
+            true => self.next(),
+            false => Some(Token::Text(Cow::Borrowed(text))),
+        }
+    }
+
+    fn tag(&mut self) -> Option<Token<'a>> {
+        let end = end(self.text)?;
+        let body = &self.advance(end + 1)[1..end];
+
+        if let Some(name) = body.strip_prefix('/') {
+            return Some(Token::End(name.trim()));
+        }
+
+        let closed = body.ends_with('/');
+        let body = body.trim_end_matches('/');
+
+        let split = body.find(char::is_whitespace).unwrap_or(body.len());
+        let (name, mut rest) = body.split_at(split);
+        let mut attributes = Vec::new();
+
+        loop {
+            rest = rest.trim_start();
+
+            if rest.is_empty() {
+                break;
+            }
+
+            let split = rest
+                .find(|c: char| c == '=' || c.is_whitespace())
+                .unwrap_or(rest.len());
+
+            let (key, tail) = rest.split_at(split);
+            let tail = tail.trim_start();
+
+            let Some(tail) = tail.strip_prefix('=') else {
+                attributes.push((key, Cow::Borrowed("")));
+                rest = tail;
+                continue;
+            };
+
+            let tail = tail.trim_start();
+
+            let (value, tail) = match tail.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let tail = &tail[1..];
+                    let end = tail.find(quote).unwrap_or(tail.len());
+                    (&tail[..end], tail.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end =
+                        tail.find(char::is_whitespace).unwrap_or(tail.len());
+                    tail.split_at(end)
+                }
+            };
+
+            attributes.push((key, unescape(value)));
+            rest = tail;
+        }
+
+        if !closed && RAW.iter().any(|raw| name.eq_ignore_ascii_case(raw)) {
+            self.raw = Some(name);
+        }
+
+        Some(Token::Start(Tag {
+            name,
+            attributes,
+            closed,
+        }))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if let Some(name) = self.raw.take() {
+            return self.raw(name);
+        }
+
+        loop {
+            if self.text.is_empty() {
+                return None;
+            }
+
+            if !self.text.starts_with('<') {
+                let end = self.text.find('<').unwrap_or(self.text.len());
+                return Some(Token::Text(unescape(self.advance(end))));
+            }
+
+            if self.text.starts_with("<!--") {
+                self.skip("-->");
+            } else if self.text.starts_with("<![CDATA[") {
+                self.advance(9);
+                let end = self.text.find("]]>").unwrap_or(self.text.len());
+                let text = self.advance(end);
+                self.skip("]]>");
+                return Some(Token::Text(Cow::Borrowed(text)));
+            } else if self.text.starts_with("<?") {
+                self.skip("?>");
+            } else if self.text.starts_with("<!") {
+                self.skip(">");
+            } else {
+                if self.text[1..].starts_with(is_name_start) {
+                    if let Some(token) = self.tag() {
+                        return Some(token);
+                    }
+                }
+
+                return Some(Token::Text(Cow::Borrowed(self.advance(1))));
+            }
+        }
+    }
+}
+
+fn end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut value = false;
+
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if value => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+
+        if !c.is_whitespace() {
+            value = c == '=';
+        }
+    }
+
+    None
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '/'
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+
+        return char::from_u32(code);
+    }
+
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "hellip" => '\u{2026}',
+        "mdash" => '\u{2014}',
+        "ndash" => '\u{2013}',
+        "lsquo" => '\u{2018}',
+        "rsquo" => '\u{2019}',
+        "ldquo" => '\u{201c}',
+        "rdquo" => '\u{201d}',
+        "copy" => '\u{a9}',
+        "shy" => '\u{ad}',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+pub fn unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((entity(&rest[1..end])?, end)));
+
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+
+    Cow::Owned(result)
+}
@@ -7,6 +7,8 @@ use std::result::Result as StdResult;
 
 use serde_json::error::Error as SerdeError;
 
+use crate::archive::StoryId;
+
 use self::ErrorKind::*;
 
 pub type Result<T> = StdResult<T, Error>;
@@ -18,13 +20,23 @@ pub enum ErrorKind {
     InvalidStory,
     UsageError,
     QueryError,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    pub path: Option<String>,
+    pub story: Option<StoryId>,
+    pub entry: Option<String>,
+    pub line: Option<usize>,
 }
 
 #[derive(Debug)]
 pub struct Error {
     kind: ErrorKind,
     message: Option<String>,
-    source: Option<Box<dyn StdError + 'static>>,
+    context: Context,
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
 }
 
 pub struct ErrorBuilder(Error);
@@ -34,6 +46,7 @@ impl ErrorBuilder {
         ErrorBuilder(Error {
             kind,
             message: None,
+            context: Context::default(),
             source: None,
         })
     }
@@ -43,7 +56,30 @@ impl ErrorBuilder {
         self
     }
 
-    pub fn source(mut self, source: impl StdError + 'static) -> Self {
+    pub fn path(mut self, path: impl ToString) -> Self {
+        self.0.context.path = Some(path.to_string());
+        self
+    }
+
+    pub fn story(mut self, story: impl Into<StoryId>) -> Self {
+        self.0.context.story = Some(story.into());
+        self
+    }
+
+    pub fn entry(mut self, entry: impl ToString) -> Self {
+        self.0.context.entry = Some(entry.to_string());
+        self
+    }
+
+    pub fn line(mut self, line: usize) -> Self {
+        self.0.context.line = Some(line);
+        self
+    }
+
+    pub fn source(
+        mut self,
+        source: impl StdError + Send + Sync + 'static,
+    ) -> Self {
         self.0.source = Some(Box::new(source));
         self
     }
@@ -77,6 +113,21 @@ impl Error {
         ErrorBuilder::new(QueryError).message(message).build()
     }
 
+    pub fn cancelled() -> Self {
+        ErrorBuilder::new(Cancelled)
+            .message("Operation was cancelled")
+            .build()
+    }
+
+    pub(crate) fn copy(&self) -> Self {
+        Error {
+            kind: self.kind.clone(),
+            message: self.message.clone(),
+            context: self.context.clone(),
+            source: None,
+        }
+    }
+
     pub fn kind(&self) -> ErrorKind {
         self.kind.clone()
     }
@@ -84,6 +135,39 @@ impl Error {
     pub fn message(&self) -> Option<&String> {
         self.message.as_ref()
     }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn with_path(mut self, path: impl ToString) -> Self {
+        self.context.path.get_or_insert(path.to_string());
+        self
+    }
+
+    pub fn with_story(mut self, story: impl Into<StoryId>) -> Self {
+        self.context.story.get_or_insert(story.into());
+        self
+    }
+
+    pub fn with_entry(mut self, entry: impl ToString) -> Self {
+        self.context.entry.get_or_insert(entry.to_string());
+        self
+    }
+
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.context.line.get_or_insert(line);
+        self
+    }
+}
+
+impl Context {
+    pub fn is_empty(&self) -> bool {
+        self.path.is_none()
+            && self.story.is_none()
+            && self.entry.is_none()
+            && self.line.is_none()
+    }
 }
 
 fn lower(message: &str) -> String {
@@ -105,12 +189,37 @@ impl Display for ErrorKind {
             InvalidStory => "Invalid story",
             UsageError => "Usage error",
             QueryError => "Query error",
+            Cancelled => "Cancelled",
         };
 
         write!(f, "{}", message)
     }
 }
 
+impl Display for Context {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut parts = Vec::new();
+
+        if let Some(path) = &self.path {
+            parts.push(format!("path {path}"));
+        }
+
+        if let Some(story) = &self.story {
+            parts.push(format!("story {story}"));
+        }
+
+        if let Some(entry) = &self.entry {
+            parts.push(format!("entry {entry}"));
+        }
+
+        if let Some(line) = &self.line {
+            parts.push(format!("line {line}"));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         let message = match &self.message {
@@ -121,12 +230,18 @@ impl Display for Error {
         let kind = self.kind();
         let info = lower(message);
 
-        write!(f, "{}, {}.", kind, info)
+        match self.context.is_empty() {
+            true => write!(f, "{}, {}.", kind, info),
+            false => write!(f, "{}, {} ({}).", kind, info, self.context),
+        }
     }
 }
 
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
-        self.source.as_ref().map(Box::as_ref)
+        match &self.source {
+            Some(source) => Some(source.as_ref()),
+            None => None,
+        }
     }
 }
@@ -1,5 +1,7 @@
 //! Fimfareader.
 
 pub mod archive;
+pub mod epub;
 pub mod error;
 pub mod prelude;
+pub mod unicode;